@@ -0,0 +1,130 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use walkdir::WalkDir;
+use zip::{result::ZipResult, write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::error::{Error, Result};
+
+pub const MCWORLD_EXTENSION: &str = "mcworld";
+const LEVEL_DAT: &str = "level.dat";
+
+/// Zips the contents of the world at `from` into an archive at `to`, with
+/// `level.dat` at the archive root as Minecraft expects. Any `extra_files` are
+/// added to the archive root, taking precedence over world files of the same
+/// name.
+pub fn pack_world(from: &Path, to: &Path, extra_files: &[(&str, &[u8])]) -> Result<()> {
+    write_archive(from, to, extra_files).map_err(|source| Error::WorldArchiveFailure {
+        source,
+        path: to.to_path_buf(),
+    })
+}
+
+fn write_archive(from: &Path, to: &Path, extra_files: &[(&str, &[u8])]) -> ZipResult<()> {
+    let mut zip = ZipWriter::new(BufWriter::new(File::create(to)?));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    for entry in WalkDir::new(from).min_depth(1).sort_by_file_name() {
+        let entry = entry.map_err(io::Error::from)?;
+        let name = archive_entry_name(entry.path().strip_prefix(from).unwrap());
+        if entry.file_type().is_dir() {
+            zip.add_directory(name, options)?;
+        } else if !extra_files.iter().any(|(extra, _)| *extra == name) {
+            zip.start_file(name, options)?;
+            io::copy(&mut File::open(entry.path())?, &mut zip)?;
+        }
+    }
+
+    for (name, content) in extra_files {
+        zip.start_file(*name, options)?;
+        zip.write_all(content)?;
+    }
+
+    zip.finish()?;
+    Ok(())
+}
+
+/// A world archive that has been checked to contain a `level.dat`.
+pub struct WorldArchive {
+    zip: ZipArchive<BufReader<File>>,
+    path: PathBuf,
+    /// The directory inside the archive that holds `level.dat`. This is empty
+    /// for well-formed `.mcworld` files, but zips made by hand often wrap the
+    /// world in a single top-level folder.
+    root: PathBuf,
+}
+
+impl WorldArchive {
+    pub fn open(path: &Path) -> Result<Self> {
+        let read_failure = |source| Error::WorldArchiveReadFailure {
+            source,
+            path: path.to_path_buf(),
+        };
+        let file = File::open(path).map_err(|e| read_failure(e.into()))?;
+        let zip = ZipArchive::new(BufReader::new(file)).map_err(read_failure)?;
+
+        let root = zip
+            .file_names()
+            .filter_map(|name| {
+                let name = Path::new(name);
+                (name.file_name()? == LEVEL_DAT).then(|| name.parent().unwrap().to_path_buf())
+            })
+            .min_by_key(|root| root.components().count())
+            .ok_or_else(|| Error::ArchiveWithoutLevelDat {
+                path: path.to_path_buf(),
+            })?;
+
+        Ok(Self {
+            zip,
+            path: path.to_path_buf(),
+            root,
+        })
+    }
+
+    /// Extracts the world inside the archive into `to`, which must not exist
+    /// yet. Entries outside the world's root directory are skipped.
+    pub fn unpack(mut self, to: &Path) -> Result<()> {
+        self.extract(to)
+            .map_err(|source| Error::WorldArchiveReadFailure {
+                source,
+                path: self.path.clone(),
+            })
+    }
+
+    fn extract(&mut self, to: &Path) -> ZipResult<()> {
+        fs::create_dir_all(to)?;
+        for index in 0..self.zip.len() {
+            let mut entry = self.zip.by_index(index)?;
+            // Entries that would escape the destination are ignored.
+            let Some(name) = entry.enclosed_name() else {
+                continue;
+            };
+            let Ok(relative) = name.strip_prefix(&self.root) else {
+                continue;
+            };
+            let path = to.join(relative);
+
+            if entry.is_dir() {
+                fs::create_dir_all(&path)?;
+            } else {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                io::copy(&mut entry, &mut File::create(&path)?)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Builds a zip entry name from a relative path. Zip entries always use `/` as
+/// the separator regardless of the platform.
+fn archive_entry_name(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
@@ -0,0 +1,250 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use walkdir::WalkDir;
+
+use crate::{
+    config::BackupConfig,
+    copy,
+    error::{Error, Result},
+    ignore::Ignore,
+    state::Direction,
+    store::HAZE_DIR,
+};
+
+const BACKUPS_DIR: &str = "backups";
+
+/// One of the two copies of a world.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Local,
+    ComMojang,
+}
+
+impl Side {
+    const ALL: [Self; 2] = [Self::Local, Self::ComMojang];
+
+    /// The side that copying a world in `direction` replaces.
+    pub fn target_of(direction: Direction) -> Self {
+        match direction {
+            Direction::Export => Self::ComMojang,
+            Direction::Import => Self::Local,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Local => "local",
+            Self::ComMojang => "com.mojang",
+        }
+    }
+}
+
+/// A single backup of a world.
+pub struct Snapshot {
+    /// The name of the snapshot directory, e.g. `20241015T134640.123Z`.
+    pub timestamp: String,
+    /// Which copy of the world was backed up.
+    pub side: Side,
+    /// The backed up world, which is in a directory named after its side
+    /// within the snapshot directory.
+    pub path: PathBuf,
+}
+
+impl Snapshot {
+    /// Reads the snapshot in the directory at `path`, unless it has no world
+    /// in it because it was never finished.
+    fn read(path: PathBuf) -> Option<Self> {
+        let timestamp = path.file_name()?.to_string_lossy().to_string();
+        Side::ALL.into_iter().find_map(|side| {
+            let world = path.join(side.as_str());
+            world.is_dir().then(|| Snapshot {
+                timestamp: timestamp.clone(),
+                side,
+                path: world,
+            })
+        })
+    }
+
+    /// Returns the timestamp in RFC 3339 form, e.g. `2024-10-15T13:46:40.123Z`.
+    pub fn time(&self) -> String {
+        let ts = &self.timestamp;
+        if ts.len() < 15 || !ts.is_char_boundary(15) {
+            return ts.clone();
+        }
+        format!(
+            "{}-{}-{}T{}:{}:{}",
+            &ts[0..4],
+            &ts[4..6],
+            &ts[6..8],
+            &ts[9..11],
+            &ts[11..13],
+            &ts[13..]
+        )
+    }
+
+    /// Returns the total size of the files in the snapshot in bytes.
+    pub fn size(&self) -> u64 {
+        WalkDir::new(&self.path)
+            .into_iter()
+            .filter_map(|entry| entry.ok()?.metadata().ok())
+            .filter(|metadata| metadata.is_file())
+            .map(|metadata| metadata.len())
+            .sum()
+    }
+}
+
+/// Keeps snapshots of worlds that Haze is about to delete in
+/// `.haze/backups/<world>/<timestamp>/<side>`.
+pub struct BackupStore {
+    root: PathBuf,
+    retention: usize,
+}
+
+impl BackupStore {
+    pub fn new(config: &BackupConfig) -> Self {
+        Self {
+            root: PathBuf::from(HAZE_DIR).join(BACKUPS_DIR),
+            retention: config.retention,
+        }
+    }
+
+    /// Moves the world at `path`, which is the `side` copy of `name`, into a
+    /// new snapshot and returns its location, or deletes it outright if
+    /// backups are disabled. Either way, `path` no longer exists afterwards.
+    pub fn discard(&self, name: &str, side: Side, path: &Path) -> Result<Option<PathBuf>> {
+        if self.retention == 0 {
+            fs::remove_dir_all(path).map_err(|source| Error::WorldAccessFailure {
+                source,
+                path: path.to_path_buf(),
+            })?;
+            return Ok(None);
+        }
+
+        let snapshot = self.root.join(name).join(timestamp()).join(side.as_str());
+        self.create_dirs(snapshot.parent().unwrap())?;
+        move_dir(path, &snapshot)?;
+        self.prune(name)?;
+
+        Ok(Some(snapshot))
+    }
+
+    /// Returns the snapshots of `name` from oldest to newest.
+    pub fn snapshots(&self, name: &str) -> Result<Vec<Snapshot>> {
+        let dir = self.root.join(name);
+        let mut snapshots = match fs::read_dir(&dir) {
+            Ok(entries) => entries
+                .map(|entry| Ok(Snapshot::read(entry?.path())))
+                .collect::<io::Result<Vec<_>>>()
+                .map_err(|source| Error::BackupFailure { source, path: dir })?
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(source) => return Err(Error::BackupFailure { source, path: dir }),
+        };
+
+        // Timestamps sort chronologically, so the oldest come first.
+        snapshots.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+        Ok(snapshots)
+    }
+
+    /// Finds the snapshot of the `side` copy of `name` taken at `timestamp`,
+    /// or the latest one if no timestamp is given.
+    pub fn find(&self, name: &str, side: Side, timestamp: Option<&str>) -> Result<Snapshot> {
+        let mut snapshots = self.snapshots(name)?;
+        if snapshots.is_empty() {
+            return Err(Error::NoBackups {
+                name: name.to_string(),
+            });
+        }
+
+        let Some(timestamp) = timestamp else {
+            snapshots.retain(|snapshot| snapshot.side == side);
+            return snapshots.pop().ok_or_else(|| Error::NoBackupsOfSide {
+                name: name.to_string(),
+                side: side.as_str(),
+            });
+        };
+
+        // Accept timestamps as printed by `haze backups` too.
+        let wanted = timestamp.replace(['-', ':'], "");
+        let snapshot = snapshots
+            .into_iter()
+            .find(|snapshot| snapshot.timestamp == wanted)
+            .ok_or_else(|| Error::BackupNotFound {
+                name: name.to_string(),
+                timestamp: timestamp.to_string(),
+            })?;
+        if snapshot.side != side {
+            return Err(Error::BackupOfOtherSide {
+                name: name.to_string(),
+                timestamp: timestamp.to_string(),
+                side: snapshot.side.as_str(),
+                target: side.as_str(),
+            });
+        }
+
+        Ok(snapshot)
+    }
+
+    /// Deletes the oldest snapshots of `name` beyond the retention count.
+    fn prune(&self, name: &str) -> Result<()> {
+        let snapshots = self.snapshots(name)?;
+        let excess = snapshots.len().saturating_sub(self.retention);
+        for snapshot in &snapshots[..excess] {
+            let dir = snapshot.path.parent().unwrap();
+            fs::remove_dir_all(dir).map_err(|source| Error::BackupFailure {
+                source,
+                path: dir.to_path_buf(),
+            })?;
+        }
+
+        Ok(())
+    }
+
+    fn create_dirs(&self, dir: &Path) -> Result<()> {
+        let backup_failure = |source| Error::BackupFailure {
+            source,
+            path: dir.to_path_buf(),
+        };
+
+        fs::create_dir_all(dir).map_err(backup_failure)?;
+        // Backups are local to each machine and shouldn't end up in git.
+        let gitignore = self.root.join(".gitignore");
+        if !gitignore.exists() {
+            fs::write(&gitignore, "*\n").map_err(backup_failure)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Moves a directory, falling back to copying when `from` and `to` are on
+/// different file systems, which is common for `com.mojang`.
+fn move_dir(from: &Path, to: &Path) -> Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    copy::copy_dir(from, to, &Ignore::default()).map_err(|source| Error::WorldCopyFailure {
+        source,
+        from: from.to_path_buf(),
+        to: to.to_path_buf(),
+    })?;
+    fs::remove_dir_all(from).map_err(|source| Error::WorldAccessFailure {
+        source,
+        path: from.to_path_buf(),
+    })
+}
+
+/// Returns the current time as a file name safe timestamp that sorts
+/// chronologically, e.g. `20241015T134640.123Z`.
+fn timestamp() -> String {
+    humantime::format_rfc3339_millis(SystemTime::now())
+        .to_string()
+        .replace(['-', ':'], "")
+}
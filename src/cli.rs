@@ -1,10 +1,17 @@
+use std::{num::NonZeroUsize, path::PathBuf};
+
 use anstyle::{AnsiColor, Color, Style};
-#[cfg(windows)]
-use clap::ValueEnum;
-use clap::{builder, Parser, Subcommand};
+use clap::{builder, Args, Parser, Subcommand, ValueEnum};
 
 #[cfg(windows)]
 use crate::com_mojang::MinecraftVersion;
+use crate::{
+    backup::Side,
+    manifest::Version,
+    packs::PackReferences,
+    sync::Compare,
+    world::{CopyOptions, OutputFormat},
+};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None, styles=get_styles())]
@@ -13,6 +20,10 @@ pub struct Cli {
     #[arg(short, long, value_name = "PATH", default_value = "config.json")]
     pub config: String,
 
+    /// Set how many files to copy at once. Defaults to the number of CPUs
+    #[arg(short, long, global = true, value_name = "N")]
+    pub jobs: Option<NonZeroUsize>,
+
     /// The Minecraft version to get the `com.mojang` directory from. To define
     /// an arbitrary path, set the `COM_MOJANG` environment variable instead
     #[cfg(windows)]
@@ -34,6 +45,32 @@ pub enum Commands {
         /// Overwrite any already existing worlds in `com.mojang`
         #[arg(short, long)]
         overwrite: bool,
+        /// Make the exported worlds use the packs listed in `config.json`,
+        /// without changing the local worlds
+        #[arg(long)]
+        link_packs: bool,
+        /// Copy the packs listed in `config.json` into the exported worlds and
+        /// make them use them, so they can be played without the project
+        #[arg(long, conflicts_with = "link_packs")]
+        embed_packs: bool,
+        /// Export the packs listed in `config.json` to `com.mojang` as well
+        #[arg(long)]
+        export_packs: bool,
+        #[command(flatten)]
+        copy: CopyArgs,
+    },
+
+    /// Copy the packs listed in `config.json` to the development pack
+    /// directories of `com.mojang`
+    ExportPacks {
+        /// Link the packs instead of copying them, so changes to them show up
+        /// without exporting them again
+        #[arg(long)]
+        symlink: bool,
+        /// Replace copies of the packs with links, even though changes made to
+        /// the copies are lost
+        #[arg(long, requires = "symlink")]
+        force: bool,
     },
 
     /// Copy `com.mojang` worlds to local worlds
@@ -42,11 +79,181 @@ pub enum Commands {
         /// The name of one or more worlds to import
         #[arg(required = true)]
         names: Vec<String>,
+        /// Import the world from a `.mcworld` or `.zip` archive instead of
+        /// `com.mojang`
+        #[arg(
+            long,
+            value_name = "PATH",
+            conflicts_with_all = ["atomic", "incremental", "force", "preserve", "pack_references"]
+        )]
+        from_archive: Option<PathBuf>,
+        /// Keep the files in the local world that Minecraft doesn't write,
+        /// such as notes or scripts, instead of replacing the whole world
+        #[arg(long)]
+        preserve: bool,
+        /// What to do with references to the packs listed in `config.json`,
+        /// instead of what is set for each world in `config.json`
+        #[arg(long, value_name = "MODE")]
+        pack_references: Option<PackReferences>,
+        #[command(flatten)]
+        copy: CopyArgs,
+    },
+
+    /// Import worlds from `com.mojang` whenever Minecraft is done with them
+    Watch {
+        /// The name of one or more worlds to watch
+        #[arg(required = true)]
+        names: Vec<String>,
+        /// How many seconds a world must go without changes before it is
+        /// imported
+        #[arg(long, value_name = "SECONDS", default_value = "10")]
+        settle: u64,
+    },
+
+    /// Export worlds, wait while they are played, and import them back
+    Session {
+        /// The name of one or more worlds to play
+        #[arg(required = true)]
+        names: Vec<String>,
+        /// How many seconds a world must go without changes before it is
+        /// imported
+        #[arg(long, value_name = "SECONDS", default_value = "10")]
+        settle: u64,
+        /// Replace either all of the worlds or none of them
+        #[arg(long)]
+        atomic: bool,
+        /// Replace worlds even if they were changed since they were last
+        /// synced or seem to be open in Minecraft
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Restore a world from one of its backups
+    Restore {
+        /// The name of the world to restore
+        name: String,
+        /// The timestamp of the backup to restore. Defaults to the latest one
+        #[arg(long, value_name = "TIMESTAMP")]
+        at: Option<String>,
+        /// Where to restore the world to
+        #[arg(short, long, value_enum, default_value = "local")]
+        target: Side,
+        /// Restore the world to `com.mojang` even if it seems to be open in
+        /// Minecraft
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// List the backups of a world
+    Backups {
+        /// The name of the world
+        name: String,
+    },
+
+    /// Package local worlds as `.mcworld` archives
+    Pack {
+        /// The name of one or more worlds to pack
+        #[arg(required = true)]
+        names: Vec<String>,
+        /// The directory to write the archives to
+        #[arg(short = 'd', long, value_name = "PATH", default_value = ".")]
+        out_dir: PathBuf,
+        /// Copy the packs listed in `config.json` into the archives and make
+        /// the worlds use them, so they can be played without the project
+        #[arg(long)]
+        embed_packs: bool,
+    },
+
+    /// Package local worlds as `.mctemplate` world templates
+    Template {
+        /// The name of one or more worlds to package
+        #[arg(required = true)]
+        names: Vec<String>,
+        /// The directory to write the templates to
+        #[arg(short = 'd', long, value_name = "PATH", default_value = ".")]
+        out_dir: PathBuf,
+        /// Set the template version, e.g. `1.2.0`
+        #[arg(long = "template-version", value_name = "VERSION")]
+        version: Option<Version>,
+        /// Set the minimum game version the template requires, e.g. `1.21.0`
+        #[arg(long, value_name = "VERSION")]
+        base_game_version: Option<Version>,
+    },
+
+    /// Show how a world in `com.mojang` differs from the local one
+    Diff {
+        /// The name of the world to compare
+        name: String,
+        /// Only show how many files were added, changed and removed
+        #[arg(long)]
+        stat: bool,
+        /// Compare the contents of files instead of their modification times
+        #[arg(long)]
+        checksum: bool,
+        /// How to print the differences
+        #[arg(short, long, value_enum, default_value = "tree")]
+        format: OutputFormat,
+    },
+
+    /// Show which worlds differ between the local project and `com.mojang`
+    #[clap(visible_alias("st"))]
+    Status {
+        /// How to print the worlds
+        #[arg(short, long, value_enum, default_value = "tree")]
+        format: OutputFormat,
+    },
+
+    /// Check that local worlds use the packs listed in `config.json` or the
+    /// ones embedded in them
+    Check {
+        /// The name of one or more worlds to check. Defaults to every local
+        /// world
+        names: Vec<String>,
     },
 
     /// List all worlds stored locally and in `com.mojang`.
     #[clap(visible_alias("ls"))]
-    List,
+    List {
+        /// How to print the worlds
+        #[arg(short, long, value_enum, default_value = "tree")]
+        format: OutputFormat,
+    },
+}
+
+/// How `export` and `import` replace worlds.
+#[derive(Args)]
+pub struct CopyArgs {
+    /// Replace either all of the worlds or none of them
+    #[arg(long)]
+    atomic: bool,
+    /// Only copy files that differ in size or modification time, and delete
+    /// files that are gone. Worlds are updated in place and not backed up
+    #[arg(long, conflicts_with = "atomic")]
+    incremental: bool,
+    /// Compare the contents of files instead of their modification times
+    #[arg(long, requires = "incremental")]
+    checksum: bool,
+    /// Replace worlds even if they were changed since they were last synced or
+    /// seem to be open in Minecraft
+    #[arg(long)]
+    force: bool,
+}
+
+impl From<CopyArgs> for CopyOptions {
+    fn from(args: CopyArgs) -> Self {
+        Self {
+            atomic: args.atomic,
+            incremental: args
+                .incremental
+                .then_some(Compare::with_checksum(args.checksum)),
+            force: args.force,
+            preserve: false,
+            link_packs: false,
+            embed_packs: false,
+            pack_references: None,
+            settled: false,
+        }
+    }
 }
 
 #[cfg(windows)]
@@ -60,6 +267,36 @@ impl ValueEnum for MinecraftVersion {
     }
 }
 
+impl ValueEnum for Side {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[Self::Local, Self::ComMojang]
+    }
+
+    fn to_possible_value(&self) -> Option<builder::PossibleValue> {
+        Some(builder::PossibleValue::new(self.as_str()))
+    }
+}
+
+impl ValueEnum for PackReferences {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[Self::Keep, Self::Remove, Self::Normalize]
+    }
+
+    fn to_possible_value(&self) -> Option<builder::PossibleValue> {
+        Some(builder::PossibleValue::new(self.as_str()))
+    }
+}
+
+impl ValueEnum for OutputFormat {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[Self::Tree, Self::Json, Self::Plain]
+    }
+
+    fn to_possible_value(&self) -> Option<builder::PossibleValue> {
+        Some(builder::PossibleValue::new(self.as_str()))
+    }
+}
+
 fn get_styles() -> builder::Styles {
     let error_style = Style::new()
         .bold()
@@ -26,20 +26,29 @@ impl MinecraftVersion {
     }
 }
 
+/// Finds the `com.mojang` directory and makes sure it has a directory for
+/// worlds.
 #[cfg(unix)]
 pub fn get_and_check() -> Result<PathBuf> {
     let path = from_env()?;
-    check_if_exists(&path)?;
+    check_if_exists(&worlds_dir(&path))?;
     Ok(path)
 }
 
+/// Finds the `com.mojang` directory and makes sure it has a directory for
+/// worlds.
 #[cfg(windows)]
 pub fn get_and_check(version: &MinecraftVersion) -> Result<PathBuf> {
     let path = from_env().or_else(|_| from_version(version))?;
-    check_if_exists(&path)?;
+    check_if_exists(&worlds_dir(&path))?;
     Ok(path)
 }
 
+/// The directory within `com.mojang` that holds the worlds.
+pub fn worlds_dir(com_mojang: &Path) -> PathBuf {
+    com_mojang.join(MC_WORLDS)
+}
+
 #[cfg(windows)]
 pub fn from_version(version: &MinecraftVersion) -> Result<PathBuf> {
     let version = match version {
@@ -54,8 +63,7 @@ pub fn from_version(version: &MinecraftVersion) -> Result<PathBuf> {
         .join(format!("Microsoft.Minecraft{version}_8wekyb3d8bbwe"))
         .join("LocalState")
         .join("games")
-        .join("com.mojang")
-        .join(MC_WORLDS);
+        .join("com.mojang");
 
     Ok(com_mojang)
 }
@@ -63,7 +71,7 @@ pub fn from_version(version: &MinecraftVersion) -> Result<PathBuf> {
 pub fn from_env() -> Result<PathBuf> {
     let com_mojang_var =
         env::var("COM_MOJANG").map_err(|source| Error::NoComMojangEnvVar { source })?;
-    Ok(PathBuf::from(com_mojang_var).join(MC_WORLDS))
+    Ok(PathBuf::from(com_mojang_var))
 }
 
 pub fn check_if_exists(dir: &Path) -> Result<()> {
@@ -1,13 +1,74 @@
-use std::{env, fs};
+use std::{collections::BTreeMap, env, fs, path::PathBuf};
 
 use json_strip_comments::CommentSettings;
 use serde::Deserialize;
 
-use crate::error::{Error, Result};
+use crate::{
+    error::{Error, Result},
+    packs::PackReferences,
+};
 
 #[derive(Deserialize)]
 pub struct Config {
+    /// The name of the project, which development packs are named after.
+    pub name: Option<String>,
     pub worlds: Vec<String>,
+    #[serde(default)]
+    pub packs: PacksConfig,
+    /// Haze specific options. These live in their own section since the rest
+    /// of the file is shared with other tools.
+    #[serde(default)]
+    pub haze: HazeConfig,
+}
+
+/// Where the project's packs live, relative to the project root.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PacksConfig {
+    pub behavior_pack: Option<PathBuf>,
+    pub resource_pack: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HazeConfig {
+    pub backups: BackupConfig,
+    pub ignore: IgnoreConfig,
+    /// Glob patterns for the files that `import --preserve` keeps in local
+    /// worlds. Every file Minecraft doesn't write is kept if this is unset.
+    pub preserve: Option<Vec<String>>,
+    /// Options for individual worlds, by name.
+    pub worlds: BTreeMap<String, WorldConfig>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WorldConfig {
+    /// What `import` does with the world's references to the project's packs.
+    pub pack_references: Option<PackReferences>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BackupConfig {
+    /// How many backups to keep per world. Setting this to `0` disables
+    /// backups.
+    pub retention: usize,
+}
+
+/// Glob patterns for files to leave out when copying worlds, on top of the
+/// ones in each world's `.hazeignore`.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct IgnoreConfig {
+    pub export: Vec<String>,
+    pub import: Vec<String>,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self { retention: 5 }
+    }
 }
 
 impl Config {
@@ -0,0 +1,72 @@
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+
+use rayon::prelude::*;
+use walkdir::WalkDir;
+
+use crate::ignore::Ignore;
+
+/// Copies the contents of the directory `from` into `to`, creating `to` if it
+/// does not exist yet, and leaving out ignored files. Directories are created
+/// up front, after which the files are copied in parallel on the global thread
+/// pool, since worlds are made up of thousands of small LevelDB files.
+pub fn copy_dir(from: &Path, to: &Path, ignore: &Ignore) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+
+    let mut files = Vec::new();
+    let entries = WalkDir::new(from)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|entry| !ignore.is_ignored(entry.path().strip_prefix(from).unwrap()));
+    for entry in entries {
+        let entry = entry?;
+        let target = to.join(entry.path().strip_prefix(from).unwrap());
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target)?;
+        } else {
+            files.push((entry.into_path(), target));
+        }
+    }
+
+    copy_files(&files)
+}
+
+/// Copies only the ignored files within `from` into `to`, so that they survive
+/// `to` replacing `from`. Nothing is copied if `from` doesn't exist.
+pub fn copy_ignored(from: &Path, to: &Path, ignore: &Ignore) -> io::Result<()> {
+    if !from.exists() {
+        return Ok(());
+    }
+
+    let mut files = Vec::new();
+    for entry in WalkDir::new(from).min_depth(1) {
+        let entry = entry?;
+        let path = entry.path().strip_prefix(from).unwrap();
+        if entry.file_type().is_file() && ignore.is_ignored(path) {
+            let target = to.join(path);
+            fs::create_dir_all(target.parent().unwrap())?;
+            files.push((entry.into_path(), target));
+        }
+    }
+
+    copy_files(&files)
+}
+
+/// Copies each `(from, to)` pair of files in parallel. The parent directories
+/// of every `to` must already exist.
+pub fn copy_files(files: &[(PathBuf, PathBuf)]) -> io::Result<()> {
+    files
+        .par_iter()
+        .try_for_each(|(from, to)| copy_file(from, to))
+}
+
+/// Copies a file along with its modification time, so that incremental syncs
+/// can tell copied files apart from ones that changed since.
+fn copy_file(from: &Path, to: &Path) -> io::Result<()> {
+    fs::copy(from, to)?;
+    let modified = fs::metadata(from)?.modified()?;
+    File::options().write(true).open(to)?.set_modified(modified)
+}
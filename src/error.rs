@@ -41,6 +41,16 @@ pub enum Error {
         pattern: String,
     },
 
+    #[error("invalid ignore pattern `{pattern}`")]
+    InvalidIgnorePattern {
+        source: glob::PatternError,
+        pattern: String,
+    },
+
+    #[error("unknown section `[{section}]` in `{}`", path.display())]
+    #[diagnostic(help("sections can either be `[export]` or `[import]`"))]
+    UnknownIgnoreSection { section: String, path: PathBuf },
+
     #[error("two local worlds have conflicting names `{}` <-> `{}`", world_a.display(), world_b.display())]
     #[diagnostic(help(
         "worlds in different directories must have unique names so they are easily identifiable"
@@ -66,10 +76,153 @@ pub enum Error {
 
     #[error("failed to copy world `{}` to `{}`", from.display(), to.display())]
     WorldCopyFailure {
-        source: fs_extra::error::Error,
+        source: io::Error,
         from: PathBuf,
         to: PathBuf,
     },
+
+    #[error("failed to copy {} of {total} worlds", errors.len())]
+    WorldsCopyFailure { total: usize, errors: Vec<Error> },
+
+    #[error("`{name}` was changed in `com.mojang` since it was last synced")]
+    #[diagnostic(help(
+        "run `haze diff {name}` to see the changes, or use --force to overwrite them"
+    ))]
+    ExportWouldClobber { name: String },
+
+    #[error("`{name}` was changed locally since it was last synced")]
+    #[diagnostic(help("use --force to overwrite the local changes"))]
+    ImportWouldClobber { name: String },
+
+    #[error("`{name}` seems to be open in Minecraft, since {reason}")]
+    #[diagnostic(help(
+        "save and quit the world in Minecraft, or use --force if you are sure it is closed"
+    ))]
+    WorldInUse { name: String, reason: String },
+
+    #[error("failed to watch worlds for changes")]
+    WatchFailure { source: notify::Error },
+
+    #[error("attempting to diff `{name}` when there is no local world matching it")]
+    DiffWithoutLocalMatch { name: String },
+
+    #[error("failed to compare the local and `com.mojang` copies of `{name}`")]
+    WorldDiffFailure { source: io::Error, name: String },
+
+    #[error("failed to back up a world at `{}`", path.display())]
+    BackupFailure { source: io::Error, path: PathBuf },
+
+    #[error("there are no backups of `{name}`")]
+    NoBackups { name: String },
+
+    #[error("there is no backup of `{name}` from `{timestamp}`")]
+    #[diagnostic(help("run `haze backups {name}` to see the available backups"))]
+    BackupNotFound { name: String, timestamp: String },
+
+    #[error("there are no backups of the {side} copy of `{name}`")]
+    #[diagnostic(help("run `haze backups {name}` to see the available backups"))]
+    NoBackupsOfSide { name: String, side: &'static str },
+
+    #[error(
+        "the backup of `{name}` from `{timestamp}` is of the {side} copy, not the {target} one"
+    )]
+    #[diagnostic(help("use --target {side} to restore it there instead"))]
+    BackupOfOtherSide {
+        name: String,
+        timestamp: String,
+        side: &'static str,
+        target: &'static str,
+    },
+
+    #[error("attempting to restore `{name}` when there is no local world matching it")]
+    #[diagnostic(help("use --target com.mojang to restore it to `com.mojang` instead"))]
+    RestoreWithoutLocalMatch { name: String },
+
+    #[error("failed to access `{}`", path.display())]
+    StoreAccessFailure { source: io::Error, path: PathBuf },
+
+    #[error("could not parse `{}`", path.display())]
+    StoreFormat {
+        source: serde_json::Error,
+        path: PathBuf,
+    },
+
+    #[error("could not parse `{}`", path.display())]
+    InvalidLevelDat {
+        source: crate::level_dat::ParseError,
+        path: PathBuf,
+    },
+
+    #[error("could not write `{}`", path.display())]
+    LevelDatWriteFailure {
+        source: crate::level_dat::WriteError,
+        path: PathBuf,
+    },
+
+    #[error("failed to write world archive `{}`", path.display())]
+    WorldArchiveFailure {
+        source: zip::result::ZipError,
+        path: PathBuf,
+    },
+
+    #[error("failed to read world archive `{}`", path.display())]
+    WorldArchiveReadFailure {
+        source: zip::result::ZipError,
+        path: PathBuf,
+    },
+
+    #[error("the archive `{}` does not contain a world", path.display())]
+    #[diagnostic(help("a world archive must contain a `level.dat` file"))]
+    ArchiveWithoutLevelDat { path: PathBuf },
+
+    #[error("attempting to import {count} worlds from a single archive")]
+    #[diagnostic(help("only one world can be imported with --from-archive at a time"))]
+    ArchiveImportWithMultipleWorlds { count: usize },
+
+    #[error("there are no packs in `config.json`")]
+    #[diagnostic(help(
+        "set `packs.behaviorPack` or `packs.resourcePack` to the directories of the project's packs"
+    ))]
+    NoProjectPacks,
+
+    #[error("failed to read the pack manifest `{}`", path.display())]
+    PackManifestAccessFailure { source: io::Error, path: PathBuf },
+
+    #[error("could not parse the pack manifest `{}`", path.display())]
+    PackManifestFormat {
+        source: serde_json::Error,
+        path: PathBuf,
+    },
+
+    #[error(
+        "found {problems} {} with the packs of worlds",
+        if *problems == 1 { "problem" } else { "problems" }
+    )]
+    #[diagnostic(help(
+        "edit the `world_behavior_packs.json` and `world_resource_packs.json` of the \
+        local worlds, or the packs embedded in them, to fix them"
+    ))]
+    PackProblems { problems: usize },
+
+    #[error("failed to export pack `{}` to `{}`", from.display(), to.display())]
+    PackExportFailure {
+        source: io::Error,
+        from: PathBuf,
+        to: PathBuf,
+    },
+
+    #[error("attempting to replace the copy of a pack at `{}` with a link", path.display())]
+    #[diagnostic(help("the copy may have changes of its own, use --force to replace it anyway"))]
+    PackLinkWouldReplace { path: PathBuf },
+
+    #[error("failed to access the pack list `{}`", path.display())]
+    WorldPacksAccessFailure { source: io::Error, path: PathBuf },
+
+    #[error("could not parse the pack list `{}`", path.display())]
+    WorldPacksFormat {
+        source: serde_json::Error,
+        path: PathBuf,
+    },
 }
 
 #[derive(Debug, Error, Diagnostic)]
@@ -0,0 +1,241 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use glob::{MatchOptions, Pattern};
+
+use crate::{
+    config::IgnoreConfig,
+    error::{Error, Result},
+    state::Direction,
+};
+
+/// The file within a local world that lists more files to ignore.
+pub const IGNORE_FILE: &str = ".hazeignore";
+
+/// The files and directories at the root of a world that Minecraft writes.
+/// World icons are matched separately, since their extension varies.
+const GAME_FILES: &[&str] = &[
+    "db",
+    "level.dat",
+    "level.dat_old",
+    "levelname.txt",
+    "world_behavior_packs.json",
+    "world_resource_packs.json",
+    "world_behavior_pack_history.json",
+    "world_resource_pack_history.json",
+    "behavior_packs",
+    "resource_packs",
+];
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// Glob patterns for files to leave out when copying a world. Patterns that
+/// contain a `/` other than a trailing one match paths relative to the world,
+/// while the others match names anywhere within it. Ignoring a directory
+/// ignores everything in it.
+#[derive(Clone, Default)]
+pub struct Ignore {
+    /// Each pattern along with whether it matches whole paths.
+    patterns: Vec<(Pattern, bool)>,
+    /// Whether everything Minecraft doesn't write is ignored as well.
+    non_game_files: bool,
+}
+
+impl Ignore {
+    /// The files that `import --preserve` keeps in local worlds, which are
+    /// the ones matching `patterns` if there are any, and otherwise every file
+    /// that Minecraft doesn't write.
+    pub fn preserved(patterns: Option<&[String]>) -> Result<Self> {
+        let Some(patterns) = patterns else {
+            return Ok(Self {
+                patterns: Vec::new(),
+                non_game_files: true,
+            });
+        };
+
+        let mut ignore = Self::default();
+        for pattern in patterns {
+            ignore.add(pattern)?;
+        }
+        Ok(ignore)
+    }
+
+    fn add(&mut self, pattern: &str) -> Result<()> {
+        let trimmed = pattern.trim_end_matches('/');
+        let anchored = trimmed.contains('/');
+        let glob = Pattern::new(trimmed.trim_start_matches('/')).map_err(|source| {
+            Error::InvalidIgnorePattern {
+                source,
+                pattern: pattern.to_string(),
+            }
+        })?;
+        self.patterns.push((glob, anchored));
+        Ok(())
+    }
+
+    /// Returns whether the file or directory at `path`, relative to the world,
+    /// is ignored.
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        if self.non_game_files && !is_game_file(path) {
+            return true;
+        }
+        if self.patterns.is_empty() {
+            return false;
+        }
+
+        let mut prefix = String::new();
+        for component in path.components() {
+            let name = component.as_os_str().to_string_lossy();
+            if !prefix.is_empty() {
+                prefix.push('/');
+            }
+            prefix.push_str(&name);
+
+            let is_match = |(pattern, anchored): &(Pattern, bool)| {
+                let candidate = if *anchored { prefix.as_str() } else { &name };
+                pattern.matches_with(candidate, MATCH_OPTIONS)
+            };
+            if self.patterns.iter().any(is_match) {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Ignores everything that `other` ignores as well.
+    fn extend(&mut self, other: &Ignore) {
+        self.patterns.extend(other.patterns.iter().cloned());
+        self.non_game_files |= other.non_game_files;
+    }
+}
+
+/// Returns whether Minecraft writes the file or directory at `path`, relative
+/// to the world, which is the case for everything within its own directories.
+fn is_game_file(path: &Path) -> bool {
+    let Some(first) = path.components().next() else {
+        return true;
+    };
+    let name = first.as_os_str().to_string_lossy();
+    GAME_FILES.contains(&name.as_ref()) || name.starts_with("world_icon.")
+}
+
+/// The files to ignore when exporting and importing a world.
+#[derive(Clone)]
+pub struct WorldIgnores {
+    export: Ignore,
+    import: Ignore,
+}
+
+impl WorldIgnores {
+    /// Compiles the patterns from `config.json`. The ignore file itself is
+    /// always ignored, so it stays with the local world.
+    pub fn from_config(config: &IgnoreConfig) -> Result<Self> {
+        let mut ignores = Self {
+            export: Ignore::default(),
+            import: Ignore::default(),
+        };
+        for ignore in [&mut ignores.export, &mut ignores.import] {
+            ignore.add(&format!("/{}", Pattern::escape(IGNORE_FILE)))?;
+        }
+        for pattern in &config.export {
+            ignores.export.add(pattern)?;
+        }
+        for pattern in &config.import {
+            ignores.import.add(pattern)?;
+        }
+
+        Ok(ignores)
+    }
+
+    /// Adds the patterns from the `.hazeignore` of the local world at `world`,
+    /// if it has one. Patterns apply to both directions unless they come after
+    /// an `[export]` or `[import]` section header, and lines starting with `#`
+    /// are comments.
+    pub fn with_file(&self, world: &Path) -> Result<Self> {
+        let path = world.join(IGNORE_FILE);
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(self.clone()),
+            Err(source) => return Err(Error::WorldAccessFailure { source, path }),
+        };
+
+        let mut ignores = self.clone();
+        let mut section = None;
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line
+                .strip_prefix('[')
+                .and_then(|line| line.strip_suffix(']'))
+            {
+                section = Some(match name {
+                    "export" => Direction::Export,
+                    "import" => Direction::Import,
+                    _ => {
+                        return Err(Error::UnknownIgnoreSection {
+                            section: name.to_string(),
+                            path: path.clone(),
+                        })
+                    }
+                });
+                continue;
+            }
+
+            match section {
+                Some(Direction::Export) => ignores.export.add(line)?,
+                Some(Direction::Import) => ignores.import.add(line)?,
+                None => {
+                    ignores.export.add(line)?;
+                    ignores.import.add(line)?;
+                }
+            }
+        }
+
+        Ok(ignores)
+    }
+
+    /// The files to ignore when copying the world in `direction`.
+    pub fn get(&self, direction: Direction) -> &Ignore {
+        match direction {
+            Direction::Export => &self.export,
+            Direction::Import => &self.import,
+        }
+    }
+
+    /// Leaves the files in `preserved` alone when importing, on top of the
+    /// ones that are already ignored.
+    pub fn preserving(mut self, preserved: &Ignore) -> Self {
+        self.import.extend(preserved);
+        self
+    }
+
+    /// Leaves the files and directories at `paths`, relative to the world, out
+    /// when importing.
+    pub fn skipping_on_import(mut self, paths: &[PathBuf]) -> Result<Self> {
+        for path in paths {
+            let pattern = path
+                .components()
+                .map(|component| Pattern::escape(&component.as_os_str().to_string_lossy()))
+                .collect::<Vec<_>>()
+                .join("/");
+            self.import.add(&format!("/{pattern}"))?;
+        }
+        Ok(self)
+    }
+
+    /// The files ignored in either direction, which never travel between the
+    /// two copies of the world.
+    pub fn either(&self) -> Ignore {
+        let mut ignore = self.export.clone();
+        ignore.extend(&self.import);
+        ignore
+    }
+}
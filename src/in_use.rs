@@ -0,0 +1,144 @@
+#[cfg(windows)]
+use std::fs::TryLockError;
+use std::{
+    fmt,
+    fs::{self, File},
+    io,
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+/// How recently a world's database must have been written to for it to count
+/// as open. Minecraft saves loaded worlds every few seconds.
+const RECENTLY_SAVED: Duration = Duration::from_secs(30);
+
+/// Why a world seems to be open in Minecraft.
+pub enum InUse {
+    /// Its `db/LOCK` file is locked, which LevelDB does while a world is open.
+    Locked,
+    /// A running process has files of the world open.
+    OpenedBy { process: String, pid: u32 },
+    /// Files in its database were written to very recently.
+    RecentlySaved,
+}
+
+impl fmt::Display for InUse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Locked => write!(f, "its database is locked"),
+            Self::OpenedBy { process, pid } => {
+                write!(f, "it is opened by `{process}` with PID {pid}")
+            }
+            Self::RecentlySaved => write!(
+                f,
+                "it was saved within the last {} seconds",
+                RECENTLY_SAVED.as_secs()
+            ),
+        }
+    }
+}
+
+/// Checks whether Minecraft seems to have the world at `path` open.
+pub fn check(path: &Path) -> Option<InUse> {
+    check_open(path).or_else(|| recently_saved(path).then_some(InUse::RecentlySaved))
+}
+
+/// Checks whether something has the world at `path` open, without going by
+/// when it was last saved. This is for worlds that are already known to have
+/// gone without changes for a while.
+pub fn check_open(path: &Path) -> Option<InUse> {
+    if is_locked(path) {
+        return Some(InUse::Locked);
+    }
+    #[cfg(target_os = "linux")]
+    if let Some((process, pid)) = opened_by(path) {
+        return Some(InUse::OpenedBy { process, pid });
+    }
+
+    None
+}
+
+#[cfg(windows)]
+fn is_locked(path: &Path) -> bool {
+    let file = match File::open(path.join("db").join("LOCK")) {
+        Ok(file) => file,
+        // Windows refuses to open files that another process holds exclusively.
+        Err(e) if e.raw_os_error() == Some(32) => return true,
+        Err(_) => return false,
+    };
+
+    matches!(file.try_lock(), Err(TryLockError::WouldBlock))
+}
+
+/// LevelDB locks `db/LOCK` with a POSIX record lock, which `flock` based
+/// locking can't see, so this asks `fcntl` whether anything would conflict
+/// with locking the whole file.
+#[cfg(unix)]
+fn is_locked(path: &Path) -> bool {
+    use std::os::fd::AsRawFd;
+
+    let Ok(file) = File::open(path.join("db").join("LOCK")) else {
+        return false;
+    };
+
+    // SAFETY: `flock` is plain data, for which all zeroes is valid. A start
+    // and length of zero cover the whole file.
+    let mut lock: libc::flock = unsafe { std::mem::zeroed() };
+    lock.l_type = libc::F_WRLCK as _;
+    lock.l_whence = libc::SEEK_SET as _;
+    // SAFETY: The file descriptor is open for as long as `file` lives, and
+    // `lock` is a valid `flock` for `F_GETLK` to fill in.
+    let result = unsafe { libc::fcntl(file.as_raw_fd(), libc::F_GETLK, &mut lock) };
+    result == 0 && i32::from(lock.l_type) != libc::F_UNLCK
+}
+
+/// Finds a process other than Haze that has a file within `path` open.
+#[cfg(target_os = "linux")]
+fn opened_by(path: &Path) -> Option<(String, u32)> {
+    let path = path.canonicalize().ok()?;
+    let own_pid = std::process::id();
+
+    for entry in fs::read_dir("/proc").ok()?.flatten() {
+        let Some(pid) = entry.file_name().to_str().and_then(|pid| pid.parse().ok()) else {
+            continue;
+        };
+        if pid == own_pid {
+            continue;
+        }
+        // Processes of other users can't be inspected, which is fine since
+        // they can't have the world open either.
+        let Ok(fds) = fs::read_dir(entry.path().join("fd")) else {
+            continue;
+        };
+        let is_open = fds
+            .flatten()
+            .filter_map(|fd| fs::read_link(fd.path()).ok())
+            .any(|file| file.starts_with(&path));
+        if is_open {
+            let process = fs::read_to_string(entry.path().join("comm"))
+                .map(|name| name.trim().to_string())
+                .unwrap_or_else(|_| "unknown".to_string());
+            return Some((process, pid));
+        }
+    }
+
+    None
+}
+
+fn recently_saved(path: &Path) -> bool {
+    let newest = || -> io::Result<Option<SystemTime>> {
+        let mut newest = None;
+        for entry in fs::read_dir(path.join("db"))? {
+            let modified = entry?.metadata()?.modified()?;
+            newest = newest.max(Some(modified));
+        }
+        Ok(newest)
+    };
+
+    match newest() {
+        Ok(Some(newest)) => newest
+            .elapsed()
+            .map_or(true, |elapsed| elapsed < RECENTLY_SAVED),
+        _ => false,
+    }
+}
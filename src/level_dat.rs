@@ -0,0 +1,486 @@
+use std::{fs, path::Path};
+
+use thiserror::Error;
+
+use crate::error::{Error, Result};
+
+/// The size of the header that precedes the NBT payload of a `level.dat`.
+const HEADER_LEN: usize = 8;
+
+/// A Bedrock `level.dat` file: an 8-byte header holding the storage version and
+/// the length of the payload, followed by a little-endian NBT compound.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelDat {
+    pub storage_version: i32,
+    /// The name of the root compound tag, which is usually empty.
+    pub name: String,
+    pub root: Compound,
+}
+
+/// An NBT tag, keeping enough information to be written back byte-for-byte.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Tag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    /// The element type is kept so that empty lists round-trip faithfully.
+    List(u8, Vec<Tag>),
+    Compound(Compound),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+/// An NBT compound. Entries keep the order they were read in.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Compound(pub Vec<(String, Tag)>);
+
+#[derive(Debug, Error)]
+pub enum ParseError {
+    #[error("unexpected end of file")]
+    UnexpectedEof,
+
+    #[error("the header declares {declared} bytes of data but {actual} are present")]
+    LengthMismatch { declared: usize, actual: usize },
+
+    #[error("unknown tag type `{0}`")]
+    UnknownTagType(u8),
+
+    #[error("the root tag must be a compound, found tag type `{0}`")]
+    RootNotCompound(u8),
+
+    #[error("negative length `{0}`")]
+    NegativeLength(i32),
+
+    #[error("string is not valid UTF-8")]
+    InvalidString,
+}
+
+#[derive(Debug, Error)]
+pub enum WriteError {
+    #[error("string of {0} bytes is longer than the 65535 bytes NBT allows")]
+    StringTooLong(usize),
+
+    #[error("length {0} is longer than NBT allows")]
+    TooLong(usize),
+}
+
+const TAG_END: u8 = 0;
+const TAG_BYTE: u8 = 1;
+const TAG_SHORT: u8 = 2;
+const TAG_INT: u8 = 3;
+const TAG_LONG: u8 = 4;
+const TAG_FLOAT: u8 = 5;
+const TAG_DOUBLE: u8 = 6;
+const TAG_BYTE_ARRAY: u8 = 7;
+const TAG_STRING: u8 = 8;
+const TAG_LIST: u8 = 9;
+const TAG_COMPOUND: u8 = 10;
+const TAG_INT_ARRAY: u8 = 11;
+const TAG_LONG_ARRAY: u8 = 12;
+
+impl LevelDat {
+    pub fn read(path: &Path) -> Result<Self> {
+        let bytes = fs::read(path).map_err(|source| Error::WorldAccessFailure {
+            source,
+            path: path.to_path_buf(),
+        })?;
+        Self::from_bytes(&bytes).map_err(|source| Error::InvalidLevelDat {
+            source,
+            path: path.to_path_buf(),
+        })
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> std::result::Result<Self, ParseError> {
+        let mut reader = Reader(bytes);
+        let storage_version = reader.i32()?;
+        let declared = reader.i32()?;
+        let declared =
+            usize::try_from(declared).map_err(|_| ParseError::NegativeLength(declared))?;
+        let actual = bytes.len() - HEADER_LEN;
+        if declared != actual {
+            return Err(ParseError::LengthMismatch { declared, actual });
+        }
+
+        let tag_type = reader.u8()?;
+        if tag_type != TAG_COMPOUND {
+            return Err(ParseError::RootNotCompound(tag_type));
+        }
+        let name = reader.string()?;
+        let root = reader.compound()?;
+
+        Ok(Self {
+            storage_version,
+            name,
+            root,
+        })
+    }
+
+    // Haze only reads `level.dat` for now, but writing is kept next to reading
+    // so the two can't drift apart.
+    #[allow(dead_code)]
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let bytes = self
+            .to_bytes()
+            .map_err(|source| Error::LevelDatWriteFailure {
+                source,
+                path: path.to_path_buf(),
+            })?;
+        fs::write(path, bytes).map_err(|source| Error::WorldAccessFailure {
+            source,
+            path: path.to_path_buf(),
+        })
+    }
+
+    #[allow(dead_code)]
+    pub fn to_bytes(&self) -> std::result::Result<Vec<u8>, WriteError> {
+        let mut payload = vec![TAG_COMPOUND];
+        write_string(&mut payload, &self.name)?;
+        write_compound(&mut payload, &self.root)?;
+
+        let mut bytes = Vec::with_capacity(HEADER_LEN + payload.len());
+        bytes.extend(self.storage_version.to_le_bytes());
+        write_len(&mut bytes, payload.len())?;
+        bytes.extend(payload);
+        Ok(bytes)
+    }
+}
+
+impl Compound {
+    pub fn get(&self, key: &str) -> Option<&Tag> {
+        self.0.iter().find(|(k, _)| k == key).map(|(_, tag)| tag)
+    }
+
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        match self.get(key)? {
+            Tag::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get_int(&self, key: &str) -> Option<i32> {
+        match self.get(key)? {
+            Tag::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn get_long(&self, key: &str) -> Option<i64> {
+        match self.get(key)? {
+            Tag::Long(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns the ints of a list tag, such as `lastOpenedWithVersion`.
+    pub fn get_int_list(&self, key: &str) -> Option<Vec<i32>> {
+        match self.get(key)? {
+            Tag::List(TAG_INT, values) => values
+                .iter()
+                .map(|value| match value {
+                    Tag::Int(value) => Some(*value),
+                    _ => None,
+                })
+                .collect(),
+            _ => None,
+        }
+    }
+}
+
+impl Tag {
+    fn tag_type(&self) -> u8 {
+        match self {
+            Self::Byte(_) => TAG_BYTE,
+            Self::Short(_) => TAG_SHORT,
+            Self::Int(_) => TAG_INT,
+            Self::Long(_) => TAG_LONG,
+            Self::Float(_) => TAG_FLOAT,
+            Self::Double(_) => TAG_DOUBLE,
+            Self::ByteArray(_) => TAG_BYTE_ARRAY,
+            Self::String(_) => TAG_STRING,
+            Self::List(..) => TAG_LIST,
+            Self::Compound(_) => TAG_COMPOUND,
+            Self::IntArray(_) => TAG_INT_ARRAY,
+            Self::LongArray(_) => TAG_LONG_ARRAY,
+        }
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> std::result::Result<[u8; N], ParseError> {
+        let (bytes, rest) = self
+            .0
+            .split_first_chunk()
+            .ok_or(ParseError::UnexpectedEof)?;
+        self.0 = rest;
+        Ok(*bytes)
+    }
+
+    fn u8(&mut self) -> std::result::Result<u8, ParseError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn i32(&mut self) -> std::result::Result<i32, ParseError> {
+        Ok(i32::from_le_bytes(self.take()?))
+    }
+
+    fn len(&mut self) -> std::result::Result<usize, ParseError> {
+        let len = self.i32()?;
+        usize::try_from(len).map_err(|_| ParseError::NegativeLength(len))
+    }
+
+    fn string(&mut self) -> std::result::Result<String, ParseError> {
+        let len = u16::from_le_bytes(self.take()?) as usize;
+        if self.0.len() < len {
+            return Err(ParseError::UnexpectedEof);
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        String::from_utf8(bytes.to_vec()).map_err(|_| ParseError::InvalidString)
+    }
+
+    fn compound(&mut self) -> std::result::Result<Compound, ParseError> {
+        let mut entries = Vec::new();
+        loop {
+            let tag_type = self.u8()?;
+            if tag_type == TAG_END {
+                return Ok(Compound(entries));
+            }
+            let key = self.string()?;
+            entries.push((key, self.payload(tag_type)?));
+        }
+    }
+
+    fn array<T>(
+        &mut self,
+        mut element: impl FnMut(&mut Self) -> std::result::Result<T, ParseError>,
+    ) -> std::result::Result<Vec<T>, ParseError> {
+        let len = self.len()?;
+        // Don't trust the declared length for the allocation; a corrupt file
+        // could claim billions of elements.
+        let mut values = Vec::with_capacity(len.min(self.0.len()));
+        for _ in 0..len {
+            values.push(element(self)?);
+        }
+        Ok(values)
+    }
+
+    fn payload(&mut self, tag_type: u8) -> std::result::Result<Tag, ParseError> {
+        let tag = match tag_type {
+            TAG_BYTE => Tag::Byte(i8::from_le_bytes(self.take()?)),
+            TAG_SHORT => Tag::Short(i16::from_le_bytes(self.take()?)),
+            TAG_INT => Tag::Int(self.i32()?),
+            TAG_LONG => Tag::Long(i64::from_le_bytes(self.take()?)),
+            TAG_FLOAT => Tag::Float(f32::from_le_bytes(self.take()?)),
+            TAG_DOUBLE => Tag::Double(f64::from_le_bytes(self.take()?)),
+            TAG_BYTE_ARRAY => Tag::ByteArray(self.array(|r| Ok(i8::from_le_bytes(r.take()?)))?),
+            TAG_STRING => Tag::String(self.string()?),
+            TAG_LIST => {
+                let element_type = self.u8()?;
+                Tag::List(element_type, self.array(|r| r.payload(element_type))?)
+            }
+            TAG_COMPOUND => Tag::Compound(self.compound()?),
+            TAG_INT_ARRAY => Tag::IntArray(self.array(Self::i32)?),
+            TAG_LONG_ARRAY => Tag::LongArray(self.array(|r| Ok(i64::from_le_bytes(r.take()?)))?),
+            unknown => return Err(ParseError::UnknownTagType(unknown)),
+        };
+        Ok(tag)
+    }
+}
+
+fn write_string(out: &mut Vec<u8>, value: &str) -> std::result::Result<(), WriteError> {
+    let len = u16::try_from(value.len()).map_err(|_| WriteError::StringTooLong(value.len()))?;
+    out.extend(len.to_le_bytes());
+    out.extend(value.as_bytes());
+    Ok(())
+}
+
+fn write_len(out: &mut Vec<u8>, len: usize) -> std::result::Result<(), WriteError> {
+    let len = i32::try_from(len).map_err(|_| WriteError::TooLong(len))?;
+    out.extend(len.to_le_bytes());
+    Ok(())
+}
+
+fn write_compound(out: &mut Vec<u8>, compound: &Compound) -> std::result::Result<(), WriteError> {
+    for (key, tag) in &compound.0 {
+        out.push(tag.tag_type());
+        write_string(out, key)?;
+        write_payload(out, tag)?;
+    }
+    out.push(TAG_END);
+    Ok(())
+}
+
+fn write_payload(out: &mut Vec<u8>, tag: &Tag) -> std::result::Result<(), WriteError> {
+    match tag {
+        Tag::Byte(value) => out.extend(value.to_le_bytes()),
+        Tag::Short(value) => out.extend(value.to_le_bytes()),
+        Tag::Int(value) => out.extend(value.to_le_bytes()),
+        Tag::Long(value) => out.extend(value.to_le_bytes()),
+        Tag::Float(value) => out.extend(value.to_le_bytes()),
+        Tag::Double(value) => out.extend(value.to_le_bytes()),
+        Tag::ByteArray(values) => {
+            write_len(out, values.len())?;
+            values
+                .iter()
+                .for_each(|value| out.extend(value.to_le_bytes()));
+        }
+        Tag::String(value) => write_string(out, value)?,
+        Tag::List(element_type, values) => {
+            out.push(*element_type);
+            write_len(out, values.len())?;
+            for value in values {
+                write_payload(out, value)?;
+            }
+        }
+        Tag::Compound(compound) => write_compound(out, compound)?,
+        Tag::IntArray(values) => {
+            write_len(out, values.len())?;
+            values
+                .iter()
+                .for_each(|value| out.extend(value.to_le_bytes()));
+        }
+        Tag::LongArray(values) => {
+            write_len(out, values.len())?;
+            values
+                .iter()
+                .for_each(|value| out.extend(value.to_le_bytes()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, path::PathBuf};
+
+    use super::*;
+
+    fn testdata(path: &str) -> PathBuf {
+        PathBuf::from("tests").join("testdata").join(path)
+    }
+
+    #[test]
+    fn reads_bedrock_level_dat() {
+        let level_dat = LevelDat::read(&testdata("level_dat/level.dat")).unwrap();
+
+        assert_eq!(level_dat.storage_version, 10);
+        assert_eq!(level_dat.root.get_str("LevelName"), Some("Foo World"));
+        assert_eq!(level_dat.root.get("GameType"), Some(&Tag::Int(1)));
+        assert_eq!(
+            level_dat.root.get("LastPlayed"),
+            Some(&Tag::Long(1729000000))
+        );
+        assert_eq!(
+            level_dat.root.get("lastOpenedWithVersion"),
+            Some(&Tag::List(
+                TAG_INT,
+                [1, 21, 40, 3, 0].into_iter().map(Tag::Int).collect()
+            ))
+        );
+        assert!(matches!(
+            level_dat.root.get("abilities"),
+            Some(Tag::Compound(_))
+        ));
+    }
+
+    #[test]
+    fn round_trips_bedrock_level_dat() {
+        let bytes = fs::read(testdata("level_dat/level.dat")).unwrap();
+        let level_dat = LevelDat::from_bytes(&bytes).unwrap();
+
+        assert_eq!(level_dat.to_bytes().unwrap(), bytes);
+    }
+
+    #[test]
+    fn writes_bedrock_level_dat() {
+        let level_dat = LevelDat::read(&testdata("level_dat/level.dat")).unwrap();
+        let path = env::temp_dir().join("haze_writes_bedrock_level_dat.dat");
+        level_dat.write(&path).unwrap();
+        let written = LevelDat::read(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(written.unwrap(), level_dat);
+    }
+
+    #[test]
+    fn round_trips_every_tag_type() {
+        let level_dat = LevelDat {
+            storage_version: 10,
+            name: String::new(),
+            root: Compound(vec![
+                ("byte".into(), Tag::Byte(-1)),
+                ("short".into(), Tag::Short(-2)),
+                ("int".into(), Tag::Int(-3)),
+                ("long".into(), Tag::Long(-4)),
+                ("float".into(), Tag::Float(0.5)),
+                ("double".into(), Tag::Double(-0.25)),
+                ("byte_array".into(), Tag::ByteArray(vec![1, -2, 3])),
+                ("string".into(), Tag::String("héllo".into())),
+                ("empty_list".into(), Tag::List(TAG_COMPOUND, vec![])),
+                (
+                    "list".into(),
+                    Tag::List(TAG_STRING, vec![Tag::String("a".into())]),
+                ),
+                (
+                    "compound".into(),
+                    Tag::Compound(Compound(vec![("nested".into(), Tag::Int(1))])),
+                ),
+                ("int_array".into(), Tag::IntArray(vec![i32::MIN, i32::MAX])),
+                (
+                    "long_array".into(),
+                    Tag::LongArray(vec![i64::MIN, i64::MAX]),
+                ),
+            ]),
+        };
+
+        let bytes = level_dat.to_bytes().unwrap();
+        assert_eq!(LevelDat::from_bytes(&bytes).unwrap(), level_dat);
+    }
+
+    #[test]
+    fn rejects_oversized_string() {
+        let level_dat = LevelDat {
+            storage_version: 10,
+            name: String::new(),
+            root: Compound(vec![(
+                "string".into(),
+                Tag::String("a".repeat(usize::from(u16::MAX) + 1)),
+            )]),
+        };
+
+        assert!(matches!(
+            level_dat.to_bytes(),
+            Err(WriteError::StringTooLong(65536))
+        ));
+    }
+
+    #[test]
+    fn rejects_empty_level_dat() {
+        let error = LevelDat::read(&testdata("basic/worlds/foo/level.dat")).unwrap_err();
+
+        assert!(matches!(
+            error,
+            Error::InvalidLevelDat {
+                source: ParseError::UnexpectedEof,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn rejects_truncated_level_dat() {
+        let bytes = fs::read(testdata("level_dat/level.dat")).unwrap();
+
+        assert!(matches!(
+            LevelDat::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(ParseError::LengthMismatch { .. })
+        ));
+    }
+}
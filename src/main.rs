@@ -1,25 +1,50 @@
+mod archive;
+mod backup;
 mod cli;
 mod com_mojang;
 mod config;
+mod copy;
 mod error;
+mod ignore;
+mod in_use;
+mod level_dat;
+mod manifest;
+mod metadata;
+mod packs;
+mod staging;
+mod state;
+mod store;
+mod sync;
+mod template;
 mod term;
+mod watch;
 mod world;
 
-use std::process;
+use std::{mem, process, time::Duration};
 
 use clap::Parser;
-use miette::Result;
+use miette::{Report, Result};
 
 use crate::{
     cli::{Cli, Commands},
     config::Config,
-    world::WorldManager,
+    error::Error,
+    sync::Compare,
+    world::{CopyOptions, WorldManager},
 };
 
 fn main() {
     let cli = Cli::parse();
     term::init_logger();
     term::init_miette();
+    staging::handle_interrupts();
+
+    if let Some(jobs) = cli.jobs {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs.get())
+            .build_global()
+            .expect("should build the global thread pool");
+    }
 
     let run = || -> Result<()> {
         let config = Config::load(cli.config)?;
@@ -29,11 +54,91 @@ fn main() {
         #[cfg(windows)]
         let com_mojang = com_mojang::get_and_check(&cli.minecraft_version)?;
 
-        let wm = WorldManager::new(config.worlds, com_mojang)?;
+        let wm = WorldManager::new(config, com_mojang)?;
         match cli.commands {
-            Commands::Export { names, overwrite } => wm.export(names, overwrite)?,
-            Commands::Import { names } => wm.import(names)?,
-            Commands::List => wm.list()?,
+            Commands::Export {
+                names,
+                overwrite,
+                link_packs,
+                embed_packs,
+                export_packs,
+                copy,
+            } => {
+                wm.export(
+                    names,
+                    overwrite,
+                    CopyOptions {
+                        link_packs,
+                        embed_packs,
+                        ..copy.into()
+                    },
+                )?;
+                if export_packs {
+                    wm.export_packs(false, false)?;
+                }
+            }
+            Commands::ExportPacks { symlink, force } => wm.export_packs(symlink, force)?,
+            Commands::Import {
+                names,
+                from_archive: None,
+                preserve,
+                pack_references,
+                copy,
+            } => wm.import(
+                names,
+                CopyOptions {
+                    preserve,
+                    pack_references,
+                    ..copy.into()
+                },
+            )?,
+            Commands::Import {
+                names,
+                from_archive: Some(archive),
+                ..
+            } => wm.import_archive(archive, names)?,
+            Commands::Watch { names, settle } => wm.watch(names, Duration::from_secs(settle))?,
+            Commands::Session {
+                names,
+                settle,
+                atomic,
+                force,
+            } => wm.session(
+                names,
+                Duration::from_secs(settle),
+                CopyOptions {
+                    atomic,
+                    force,
+                    ..CopyOptions::default()
+                },
+            )?,
+            Commands::Restore {
+                name,
+                at,
+                target,
+                force,
+            } => wm.restore(name, at, target, force)?,
+            Commands::Backups { name } => wm.backups(name)?,
+            Commands::Pack {
+                names,
+                out_dir,
+                embed_packs,
+            } => wm.pack(names, out_dir, embed_packs)?,
+            Commands::Template {
+                names,
+                out_dir,
+                version,
+                base_game_version,
+            } => wm.template(names, out_dir, version, base_game_version)?,
+            Commands::Diff {
+                name,
+                stat,
+                checksum,
+                format,
+            } => wm.diff(name, stat, Compare::with_checksum(checksum), format)?,
+            Commands::Status { format } => wm.status(format)?,
+            Commands::Check { names } => wm.check(names)?,
+            Commands::List { format } => wm.list(format)?,
         }
 
         Ok(())
@@ -41,11 +146,21 @@ fn main() {
 
     match run() {
         Ok(_) => process::exit(0),
-        Err(error) => {
-            let error = format!("{error:?}");
-            // Trim the initial whitespace.
-            log::error!("{}", &error[13..]);
+        Err(mut report) => {
+            log_error(&report);
+            // Each world that failed to copy gets an error of its own.
+            if let Some(Error::WorldsCopyFailure { errors, .. }) = report.downcast_mut() {
+                for error in mem::take(errors) {
+                    log_error(&error.into());
+                }
+            }
             process::exit(1);
         }
     }
 }
+
+fn log_error(report: &Report) {
+    let error = format!("{report:?}");
+    // Trim the initial whitespace.
+    log::error!("{}", &error[13..]);
+}
@@ -0,0 +1,83 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize};
+use uuid::Uuid;
+
+/// A `manifest.json` as used by add-on packs and world templates.
+#[derive(Serialize, Deserialize)]
+pub struct Manifest {
+    pub format_version: u32,
+    pub header: Header,
+    pub modules: Vec<Module>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Header {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub uuid: Uuid,
+    pub version: Version,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lock_template_options: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_game_version: Option<Version>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Module {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub uuid: Uuid,
+    pub version: Version,
+}
+
+/// A `[major, minor, patch]` version. Manifests may also spell it as a
+/// `"major.minor.patch"` string, which is accepted when reading.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Version(pub [u32; 3]);
+
+impl Default for Version {
+    fn default() -> Self {
+        Self([1, 0, 0])
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [major, minor, patch] = self.0;
+        write!(f, "{major}.{minor}.{patch}")
+    }
+}
+
+impl FromStr for Version {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s
+            .split('.')
+            .map(|part| part.parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("invalid version `{s}`: {e}"))?;
+
+        <[u32; 3]>::try_from(parts)
+            .map(Self)
+            .map_err(|_| format!("invalid version `{s}`: expected `major.minor.patch`"))
+    }
+}
+
+impl<'de> Deserialize<'de> for Version {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Array([u32; 3]),
+            String(String),
+        }
+
+        match Repr::deserialize(deserializer)? {
+            Repr::Array(version) => Ok(Self(version)),
+            Repr::String(version) => version.parse().map_err(serde::de::Error::custom),
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use std::{
+    fmt, fs,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::level_dat::LevelDat;
+
+/// Human-readable information about a world, read from its `levelname.txt`
+/// and `level.dat`. Every field is optional since worlds may be missing these
+/// files or have them corrupted.
+#[derive(Default)]
+pub struct WorldMetadata {
+    pub level_name: Option<String>,
+    pub game_type: Option<GameType>,
+    pub last_opened_with_version: Option<String>,
+    pub last_played: Option<SystemTime>,
+}
+
+pub enum GameType {
+    Survival,
+    Creative,
+    Adventure,
+    Spectator,
+    Other(i32),
+}
+
+impl WorldMetadata {
+    pub fn read(path: &Path) -> Self {
+        let mut metadata = match LevelDat::read(&path.join("level.dat")) {
+            Ok(level_dat) => Self::from_level_dat(&level_dat),
+            Err(error) => {
+                log::debug!("ignoring metadata of `{}`: {error}", path.display());
+                Self::default()
+            }
+        };
+
+        // Minecraft shows the name from `levelname.txt` in the world list, so
+        // it takes precedence over the one in `level.dat`.
+        if let Some(name) = fs::read_to_string(path.join("levelname.txt"))
+            .ok()
+            .and_then(|name| non_empty(name.trim()))
+        {
+            metadata.level_name = Some(name);
+        }
+
+        metadata
+    }
+
+    fn from_level_dat(level_dat: &LevelDat) -> Self {
+        let root = &level_dat.root;
+        Self {
+            level_name: root.get_str("LevelName").and_then(non_empty),
+            game_type: root.get_int("GameType").map(GameType::from),
+            last_opened_with_version: root.get_int_list("lastOpenedWithVersion").map(|version| {
+                // The fifth element is a flag for beta versions, not a part of
+                // the version number.
+                version
+                    .iter()
+                    .take(4)
+                    .map(|part| part.to_string())
+                    .collect::<Vec<_>>()
+                    .join(".")
+            }),
+            last_played: root
+                .get_long("LastPlayed")
+                .and_then(|secs| u64::try_from(secs).ok())
+                .filter(|&secs| secs > 0)
+                .map(|secs| UNIX_EPOCH + Duration::from_secs(secs)),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.level_name.is_none()
+            && self.game_type.is_none()
+            && self.last_opened_with_version.is_none()
+            && self.last_played.is_none()
+    }
+}
+
+impl fmt::Display for WorldMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(level_name) = &self.level_name {
+            parts.push(format!("\"{level_name}\""));
+        }
+        if let Some(game_type) = &self.game_type {
+            parts.push(game_type.to_string());
+        }
+        if let Some(version) = &self.last_opened_with_version {
+            parts.push(format!("v{version}"));
+        }
+        if let Some(last_played) = self.last_played {
+            parts.push(format!(
+                "last played {}",
+                humantime::format_rfc3339_seconds(last_played)
+            ));
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+impl From<i32> for GameType {
+    fn from(value: i32) -> Self {
+        match value {
+            0 => Self::Survival,
+            1 => Self::Creative,
+            2 => Self::Adventure,
+            6 => Self::Spectator,
+            other => Self::Other(other),
+        }
+    }
+}
+
+impl fmt::Display for GameType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Survival => write!(f, "survival"),
+            Self::Creative => write!(f, "creative"),
+            Self::Adventure => write!(f, "adventure"),
+            Self::Spectator => write!(f, "spectator"),
+            Self::Other(value) => write!(f, "game type {value}"),
+        }
+    }
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    (!value.is_empty()).then(|| value.to_string())
+}
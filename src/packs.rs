@@ -0,0 +1,591 @@
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use json_strip_comments::CommentSettings;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    config::PacksConfig,
+    copy,
+    error::{Error, Result},
+    ignore::Ignore,
+    manifest::{Manifest, Version},
+    staging::Staging,
+};
+
+/// What `import` does with a world's references to the project's packs, which
+/// Minecraft adds while the world is played with them.
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PackReferences {
+    /// Leave them as Minecraft wrote them.
+    #[default]
+    Keep,
+    /// Remove them, so the world doesn't depend on the project's packs.
+    Remove,
+    /// Point them at the current version of each pack, and drop duplicates.
+    Normalize,
+}
+
+impl PackReferences {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Keep => "keep",
+            Self::Remove => "remove",
+            Self::Normalize => "normalize",
+        }
+    }
+}
+
+/// Whether a pack adds behavior or resources.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PackKind {
+    Behavior,
+    Resource,
+}
+
+impl PackKind {
+    pub const ALL: [Self; 2] = [Self::Behavior, Self::Resource];
+
+    /// The file in a world that lists the packs of this kind it uses.
+    pub fn world_file(&self) -> &'static str {
+        match self {
+            Self::Behavior => "world_behavior_packs.json",
+            Self::Resource => "world_resource_packs.json",
+        }
+    }
+
+    /// The file in a world that lists every pack of this kind the world has
+    /// ever used.
+    pub fn history_file(&self) -> &'static str {
+        match self {
+            Self::Behavior => "world_behavior_pack_history.json",
+            Self::Resource => "world_resource_pack_history.json",
+        }
+    }
+
+    /// The directory in `com.mojang` that Minecraft loads packs of this kind
+    /// from while they are being worked on.
+    pub fn development_dir(&self) -> &'static str {
+        match self {
+            Self::Behavior => "development_behavior_packs",
+            Self::Resource => "development_resource_packs",
+        }
+    }
+
+    /// The directory in a world that packs of this kind are embedded in.
+    pub fn embedded_dir(&self) -> &'static str {
+        match self {
+            Self::Behavior => "behavior_packs",
+            Self::Resource => "resource_packs",
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Behavior => "behavior",
+            Self::Resource => "resource",
+        }
+    }
+
+    fn suffix(&self) -> &'static str {
+        match self {
+            Self::Behavior => "bp",
+            Self::Resource => "rp",
+        }
+    }
+}
+
+/// A pack along with the UUID and version from its `manifest.json`, such as
+/// one of the project's packs listed in the `packs` section of `config.json`.
+pub struct Pack {
+    pub kind: PackKind,
+    pub path: PathBuf,
+    pub uuid: Uuid,
+    pub version: Version,
+}
+
+impl Pack {
+    fn load(kind: PackKind, path: &Path) -> Result<Self> {
+        let manifest_path = path.join("manifest.json");
+        let mut content = fs::read_to_string(&manifest_path).map_err(|source| {
+            Error::PackManifestAccessFailure {
+                source,
+                path: manifest_path.clone(),
+            }
+        })?;
+        strip_comments(&mut content);
+        let manifest: Manifest =
+            serde_json::from_str(&content).map_err(|source| Error::PackManifestFormat {
+                source,
+                path: manifest_path,
+            })?;
+
+        Ok(Self {
+            kind,
+            path: path.to_path_buf(),
+            uuid: manifest.header.uuid,
+            version: manifest.header.version,
+        })
+    }
+
+    /// The name of the pack's directory when it is deployed somewhere, which
+    /// is based on the project's name.
+    fn dir_name(&self, project_name: &str) -> String {
+        format!("{project_name}_{}", self.kind.suffix())
+    }
+
+    /// Where the pack goes in `com.mojang` during development.
+    pub fn development_path(&self, com_mojang: &Path, project_name: &str) -> PathBuf {
+        com_mojang
+            .join(self.kind.development_dir())
+            .join(self.dir_name(project_name))
+    }
+
+    /// Where the pack goes when it is embedded in the world at `world`.
+    pub fn embedded_path(&self, world: &Path, project_name: &str) -> PathBuf {
+        world
+            .join(self.kind.embedded_dir())
+            .join(self.dir_name(project_name))
+    }
+}
+
+/// Reads the manifests of the project's packs. Fails if the project doesn't
+/// have any packs.
+pub fn load_project_packs(config: &PacksConfig) -> Result<Vec<Pack>> {
+    let packs = [
+        (PackKind::Behavior, &config.behavior_pack),
+        (PackKind::Resource, &config.resource_pack),
+    ]
+    .into_iter()
+    .filter_map(|(kind, path)| Some((kind, path.as_ref()?)))
+    .map(|(kind, path)| Pack::load(kind, path))
+    .collect::<Result<Vec<_>>>()?;
+
+    if packs.is_empty() {
+        return Err(Error::NoProjectPacks);
+    }
+    Ok(packs)
+}
+
+/// An entry of `world_behavior_packs.json` or `world_resource_packs.json`.
+/// Fields Haze doesn't know about, such as `subpack`, are kept as they are.
+#[derive(Serialize, Deserialize)]
+pub struct PackReference {
+    pub pack_id: Uuid,
+    pub version: Version,
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Reads the packs of `kind` that the world at `world` uses, which is none if
+/// it doesn't list any.
+pub fn read_references(world: &Path, kind: PackKind) -> Result<Vec<PackReference>> {
+    let path = world.join(kind.world_file());
+    let mut content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(source) => return Err(Error::WorldPacksAccessFailure { source, path }),
+    };
+
+    strip_comments(&mut content);
+    serde_json::from_str(&content).map_err(|source| Error::WorldPacksFormat { source, path })
+}
+
+/// Removes the comments from the contents of a pack JSON file, which Minecraft
+/// allows just like `config.json` does.
+fn strip_comments(content: &mut str) {
+    json_strip_comments::strip_comments_in_place(content, CommentSettings::c_style(), true)
+        .unwrap();
+}
+
+/// Writes the packs of `kind` that the world at `world` uses, removing the
+/// file if there are none.
+fn write_references(world: &Path, kind: PackKind, references: &[PackReference]) -> Result<()> {
+    let path = world.join(kind.world_file());
+    let result = if references.is_empty() {
+        fs::remove_file(&path)
+    } else {
+        fs::write(&path, serde_json::to_string_pretty(references).unwrap())
+    };
+    result.map_err(|source| Error::WorldPacksAccessFailure { source, path })
+}
+
+/// Makes the world at `world` use `packs`, keeping the other packs it already
+/// uses. Packs it already uses are updated to the version of the project's.
+pub fn link(world: &Path, packs: &[Pack]) -> Result<()> {
+    for kind in PackKind::ALL {
+        let mut packs = packs.iter().filter(|pack| pack.kind == kind).peekable();
+        if packs.peek().is_none() {
+            continue;
+        }
+
+        let mut references = read_references(world, kind)?;
+        for pack in packs {
+            match references
+                .iter_mut()
+                .find(|reference| reference.pack_id == pack.uuid)
+            {
+                Some(reference) => reference.version = pack.version,
+                None => references.push(PackReference {
+                    pack_id: pack.uuid,
+                    version: pack.version,
+                    other: serde_json::Map::new(),
+                }),
+            }
+        }
+        write_references(world, kind, &references)?;
+    }
+
+    Ok(())
+}
+
+/// Removes or normalizes the references to `packs` in the world at `world`,
+/// both in the packs it uses and in its pack history. Files are only written
+/// if something changed, and ones that end up empty are removed.
+pub fn clean_references(world: &Path, packs: &[Pack], mode: PackReferences) -> Result<()> {
+    if matches!(mode, PackReferences::Keep) {
+        return Ok(());
+    }
+
+    for kind in PackKind::ALL {
+        let project_pack = |uuid: Uuid| {
+            packs
+                .iter()
+                .find(|pack| pack.kind == kind && pack.uuid == uuid)
+        };
+
+        let mut references = read_references(world, kind)?;
+        let count = references.len();
+        let mut changed = false;
+        let mut seen = Vec::new();
+        references.retain_mut(|reference| {
+            let Some(pack) = project_pack(reference.pack_id) else {
+                return true;
+            };
+            match mode {
+                PackReferences::Keep => true,
+                PackReferences::Remove => false,
+                PackReferences::Normalize if seen.contains(&pack.uuid) => false,
+                PackReferences::Normalize => {
+                    seen.push(pack.uuid);
+                    changed |= reference.version != pack.version;
+                    reference.version = pack.version;
+                    true
+                }
+            }
+        });
+        if changed || references.len() != count {
+            write_references(world, kind, &references)?;
+        }
+
+        clean_history(world, kind, mode, |uuid| {
+            project_pack(uuid).map(|pack| pack.version)
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Removes or normalizes the entries of a world's pack history for which
+/// `project_version` returns the version of the project's pack.
+fn clean_history(
+    world: &Path,
+    kind: PackKind,
+    mode: PackReferences,
+    project_version: impl Fn(Uuid) -> Option<Version>,
+) -> Result<()> {
+    let path = world.join(kind.history_file());
+    let mut content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(source) => return Err(Error::WorldPacksAccessFailure { source, path }),
+    };
+    strip_comments(&mut content);
+    let mut history: serde_json::Value =
+        serde_json::from_str(&content).map_err(|source| Error::WorldPacksFormat {
+            source,
+            path: path.clone(),
+        })?;
+    let Some(entries) = history
+        .get_mut("packs")
+        .and_then(serde_json::Value::as_array_mut)
+    else {
+        return Ok(());
+    };
+
+    let original = entries.clone();
+    let mut seen = Vec::new();
+    entries.retain_mut(|entry| {
+        let uuid = entry
+            .get("uuid")
+            .and_then(serde_json::Value::as_str)
+            .and_then(|uuid| Uuid::parse_str(uuid).ok());
+        let Some((uuid, version)) = uuid.and_then(|uuid| Some((uuid, project_version(uuid)?)))
+        else {
+            return true;
+        };
+        match mode {
+            PackReferences::Keep => true,
+            PackReferences::Remove => false,
+            PackReferences::Normalize if seen.contains(&uuid) => false,
+            PackReferences::Normalize => {
+                seen.push(uuid);
+                entry["version"] = serde_json::to_value(version).unwrap();
+                true
+            }
+        }
+    });
+    if *entries == original {
+        return Ok(());
+    }
+
+    if entries.is_empty() {
+        return fs::remove_file(&path)
+            .map_err(|source| Error::WorldPacksAccessFailure { source, path });
+    }
+    let content = serde_json::to_string_pretty(&history).unwrap();
+    fs::write(&path, content).map_err(|source| Error::WorldPacksAccessFailure { source, path })
+}
+
+/// Something wrong with the packs of a world, found by [`check`].
+pub enum PackProblem {
+    /// The world uses a pack that neither the project nor the world has.
+    Unknown {
+        kind: PackKind,
+        uuid: Uuid,
+        version: Version,
+    },
+    /// The world uses a different version of a pack than the one it gets.
+    VersionMismatch {
+        kind: PackKind,
+        uuid: Uuid,
+        referenced: Version,
+        provided: Version,
+        embedded: bool,
+    },
+    /// The world embeds a pack that it doesn't use.
+    UnusedEmbedded { path: PathBuf },
+    /// The world embeds a directory that isn't a valid pack.
+    InvalidEmbedded { path: PathBuf },
+}
+
+impl fmt::Display for PackProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unknown {
+                kind,
+                uuid,
+                version,
+            } => write!(
+                f,
+                "uses version {version} of {} pack `{uuid}`, which is neither one of the project's packs nor embedded in the world",
+                kind.as_str()
+            ),
+            Self::VersionMismatch {
+                kind,
+                uuid,
+                referenced,
+                provided,
+                embedded,
+            } => write!(
+                f,
+                "uses version {referenced} of {} pack `{uuid}`, but the {} pack is version {provided}",
+                kind.as_str(),
+                if *embedded { "embedded" } else { "project's" }
+            ),
+            Self::UnusedEmbedded { path } => {
+                write!(f, "embeds `{}`, which it doesn't use", path.display())
+            }
+            Self::InvalidEmbedded { path } => write!(
+                f,
+                "embeds `{}`, which has no valid `manifest.json`",
+                path.display()
+            ),
+        }
+    }
+}
+
+/// Checks the packs that the world at `world` uses against `packs` and the
+/// packs embedded in the world, which take precedence.
+pub fn check(world: &Path, packs: &[Pack]) -> Result<Vec<PackProblem>> {
+    let mut problems = Vec::new();
+    for kind in PackKind::ALL {
+        let mut embedded = Vec::new();
+        let paths = embedded_entries(world, kind)?;
+        for path in paths.into_iter().filter(|path| path.is_dir()) {
+            match Pack::load(kind, &path) {
+                Ok(pack) => embedded.push(pack),
+                Err(_) => problems.push(PackProblem::InvalidEmbedded { path }),
+            }
+        }
+
+        let references = read_references(world, kind)?;
+        for reference in &references {
+            let uuid = reference.pack_id;
+            let provided = embedded
+                .iter()
+                .map(|pack| (pack, true))
+                .chain(packs.iter().map(|pack| (pack, false)))
+                .find(|(pack, _)| pack.kind == kind && pack.uuid == uuid);
+            match provided {
+                None => problems.push(PackProblem::Unknown {
+                    kind,
+                    uuid,
+                    version: reference.version,
+                }),
+                Some((pack, embedded)) if pack.version != reference.version => {
+                    problems.push(PackProblem::VersionMismatch {
+                        kind,
+                        uuid,
+                        referenced: reference.version,
+                        provided: pack.version,
+                        embedded,
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+
+        problems.extend(
+            embedded
+                .into_iter()
+                .filter(|pack| {
+                    !references
+                        .iter()
+                        .any(|reference| reference.pack_id == pack.uuid)
+                })
+                .map(|pack| PackProblem::UnusedEmbedded { path: pack.path }),
+        );
+    }
+
+    Ok(problems)
+}
+
+/// Everything in the directory of the world at `world` that packs of `kind`
+/// are embedded in, sorted by name.
+fn embedded_entries(world: &Path, kind: PackKind) -> Result<Vec<PathBuf>> {
+    let dir = world.join(kind.embedded_dir());
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let access_failure = |source| Error::WorldAccessFailure {
+        source,
+        path: dir.clone(),
+    };
+    let mut paths = fs::read_dir(&dir)
+        .map_err(access_failure)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()
+        .map_err(access_failure)?;
+    paths.sort();
+    Ok(paths)
+}
+
+/// The packs embedded in the world at `world` that have the UUID of one of
+/// `packs`, relative to the world. These are left over from exporting with
+/// `--embed-packs`. If nothing else is embedded next to them, their parent
+/// directory is returned instead.
+pub fn embedded_copies(world: &Path, packs: &[Pack]) -> Result<Vec<PathBuf>> {
+    let mut copies = Vec::new();
+    for kind in PackKind::ALL {
+        let entries = embedded_entries(world, kind)?;
+        let is_copy = |path: &PathBuf| {
+            path.is_dir()
+                && Pack::load(kind, path).is_ok_and(|embedded| {
+                    packs
+                        .iter()
+                        .any(|pack| pack.kind == kind && pack.uuid == embedded.uuid)
+                })
+        };
+        let kind_copies: Vec<_> = entries.iter().filter(|path| is_copy(path)).collect();
+        if kind_copies.is_empty() {
+            continue;
+        }
+
+        if kind_copies.len() == entries.len() {
+            copies.push(PathBuf::from(kind.embedded_dir()));
+        } else {
+            copies.extend(
+                kind_copies
+                    .into_iter()
+                    .filter_map(|path| Some(path.strip_prefix(world).ok()?.to_path_buf())),
+            );
+        }
+    }
+    Ok(copies)
+}
+
+/// Copies `packs` into the world at `world` and makes it use them, so that the
+/// world can be played without the project. Packs that were embedded before
+/// are replaced.
+pub fn embed(world: &Path, packs: &[Pack], project_name: &str) -> Result<()> {
+    for pack in packs {
+        let to = pack.embedded_path(world, project_name);
+        let export_failure = |source| Error::PackExportFailure {
+            source,
+            from: pack.path.clone(),
+            to: to.clone(),
+        };
+        if to.exists() {
+            fs::remove_dir_all(&to).map_err(export_failure)?;
+        }
+        copy::copy_dir(&pack.path, &to, &Ignore::default()).map_err(export_failure)?;
+    }
+
+    link(world, packs)
+}
+
+/// Puts a copy of `pack` at `to`, or a symbolic link to it if `symlink` is
+/// set, replacing whatever was there before. A copy is only replaced with a
+/// link if `force` is set, since it may have changes of its own.
+pub fn deploy(pack: &Pack, to: &Path, symlink: bool, force: bool) -> Result<()> {
+    let export_failure = |source| Error::PackExportFailure {
+        source,
+        from: pack.path.clone(),
+        to: to.to_path_buf(),
+    };
+
+    // Links are replaced outright, since there is nothing in them to lose.
+    if fs::symlink_metadata(to).is_ok_and(|metadata| metadata.is_symlink()) {
+        remove_symlink(to).map_err(export_failure)?;
+    }
+
+    if symlink {
+        if to.exists() {
+            if !force {
+                return Err(Error::PackLinkWouldReplace {
+                    path: to.to_path_buf(),
+                });
+            }
+            fs::remove_dir_all(to).map_err(export_failure)?;
+        }
+        fs::create_dir_all(to.parent().unwrap()).map_err(export_failure)?;
+        let from = pack.path.canonicalize().map_err(export_failure)?;
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(from, to).map_err(export_failure)?;
+        #[cfg(windows)]
+        std::os::windows::fs::symlink_dir(from, to).map_err(export_failure)?;
+        return Ok(());
+    }
+
+    let staging = Staging::new(to)?;
+    copy::copy_dir(&pack.path, staging.path(), &Ignore::default()).map_err(export_failure)?;
+    if let Some(old) = staging.commit()?.old {
+        fs::remove_dir_all(old).map_err(export_failure)?;
+    }
+
+    Ok(())
+}
+
+fn remove_symlink(path: &Path) -> io::Result<()> {
+    // Windows treats links to directories as directories.
+    #[cfg(windows)]
+    return fs::remove_dir(path);
+    #[cfg(not(windows))]
+    fs::remove_file(path)
+}
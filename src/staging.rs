@@ -0,0 +1,190 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process,
+    sync::Mutex,
+};
+
+use crate::error::{Error, Result};
+
+const STAGING_SUFFIX: &str = ".haze-staging";
+const OLD_SUFFIX: &str = ".haze-old";
+
+/// Staging directories that are currently being written to, so they can be
+/// cleaned up if Haze is interrupted.
+static ACTIVE: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// A directory next to a world's final location that a new copy of the world is
+/// written to. The world is only replaced once the copy is complete, so a
+/// failed or interrupted copy never leaves a half-written world behind.
+pub struct Staging {
+    path: PathBuf,
+    target: PathBuf,
+}
+
+impl Staging {
+    pub fn new(target: &Path) -> Result<Self> {
+        let path = sibling(target, STAGING_SUFFIX);
+        let access_failure = |source| Error::WorldAccessFailure {
+            source,
+            path: path.clone(),
+        };
+
+        if path.exists() {
+            fs::remove_dir_all(&path).map_err(access_failure)?;
+        }
+        ACTIVE.lock().unwrap().push(path.clone());
+        fs::create_dir_all(&path).map_err(access_failure)?;
+
+        Ok(Self {
+            path,
+            target: target.to_path_buf(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn target(&self) -> &Path {
+        &self.target
+    }
+
+    /// Swaps the staged world into place. If a world already exists at the
+    /// target, it is moved aside rather than deleted, so the caller can decide
+    /// what to do with it.
+    pub fn commit(self) -> Result<Committed> {
+        let old = if self.target.exists() {
+            let old = sibling(&self.target, OLD_SUFFIX);
+            rename(&self.target, &old)?;
+            Some(old)
+        } else {
+            None
+        };
+
+        if let Err(error) = rename(&self.path, &self.target) {
+            // Put the original world back so nothing is lost.
+            if let Some(old) = &old {
+                let _ = fs::rename(old, &self.target);
+            }
+            return Err(error);
+        }
+
+        Ok(Committed {
+            target: self.target.clone(),
+            old,
+        })
+    }
+}
+
+/// A world that was swapped into place by [`Staging::commit`].
+pub struct Committed {
+    pub target: PathBuf,
+    /// Where the world that was replaced has been moved to, if there was one.
+    pub old: Option<PathBuf>,
+}
+
+impl Committed {
+    /// Undoes the commit by deleting the new world and putting the replaced
+    /// one back.
+    pub fn revert(&self) -> Result<()> {
+        fs::remove_dir_all(&self.target).map_err(|source| Error::WorldAccessFailure {
+            source,
+            path: self.target.clone(),
+        })?;
+        if let Some(old) = &self.old {
+            rename(old, &self.target)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for Staging {
+    fn drop(&mut self) {
+        // After a successful commit the staging directory no longer exists.
+        if self.path.exists() {
+            let _ = fs::remove_dir_all(&self.path);
+        }
+        ACTIVE.lock().unwrap().retain(|path| path != &self.path);
+    }
+}
+
+/// What was left behind in a directory by a previous run that didn't finish.
+pub enum Leftover {
+    /// A partial copy that was never committed.
+    Staging(PathBuf),
+    /// A world that was moved aside by [`Staging::commit`], along with the
+    /// location it was moved from.
+    Old { path: PathBuf, target: PathBuf },
+}
+
+/// Returns whether `path` is a directory that Haze creates while replacing
+/// worlds, rather than a world itself.
+pub fn is_leftover(path: &Path) -> bool {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    name.starts_with('.') && (name.ends_with(STAGING_SUFFIX) || name.ends_with(OLD_SUFFIX))
+}
+
+/// Finds everything left behind by previous runs in `dir`.
+pub fn leftovers(dir: &Path) -> Result<Vec<Leftover>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(source) => {
+            return Err(Error::WorldAccessFailure {
+                source,
+                path: dir.to_path_buf(),
+            })
+        }
+    };
+
+    let mut leftovers = Vec::new();
+    for entry in entries {
+        let path = entry
+            .map_err(|source| Error::WorldAccessFailure {
+                source,
+                path: dir.to_path_buf(),
+            })?
+            .path();
+        if !is_leftover(&path) {
+            continue;
+        }
+
+        let name = path.file_name().unwrap().to_string_lossy();
+        if let Some(world) = name.strip_suffix(OLD_SUFFIX) {
+            let target = dir.join(&world[1..]);
+            leftovers.push(Leftover::Old { path, target });
+        } else {
+            leftovers.push(Leftover::Staging(path));
+        }
+    }
+
+    Ok(leftovers)
+}
+
+/// Removes any staging directories that are being written to when Haze is
+/// interrupted with Ctrl-C.
+pub fn handle_interrupts() {
+    ctrlc::set_handler(|| {
+        for path in ACTIVE.lock().unwrap().drain(..) {
+            let _ = fs::remove_dir_all(path);
+        }
+        log::warn!("interrupted, cleaned up partially copied worlds");
+        process::exit(130);
+    })
+    .expect("should set Ctrl-C handler");
+}
+
+pub fn rename(from: &Path, to: &Path) -> Result<()> {
+    fs::rename(from, to).map_err(|source| Error::WorldAccessFailure {
+        source,
+        path: from.to_path_buf(),
+    })
+}
+
+/// Returns a hidden path next to `target`, e.g. `.foo.haze-staging` for `foo`.
+fn sibling(target: &Path, suffix: &str) -> PathBuf {
+    let name = target.file_name().unwrap().to_string_lossy();
+    target.with_file_name(format!(".{name}{suffix}"))
+}
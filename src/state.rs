@@ -0,0 +1,111 @@
+use std::{
+    collections::BTreeMap,
+    io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::Result,
+    ignore::WorldIgnores,
+    store::{self, HAZE_DIR},
+    sync::{self, Compare},
+};
+
+const STATE_FILE: &str = "state.json";
+
+/// Which way a world was last copied.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Export,
+    Import,
+}
+
+impl Direction {
+    pub fn verb(&self) -> &'static str {
+        match self {
+            Self::Export => "exported",
+            Self::Import => "imported",
+        }
+    }
+}
+
+/// What a world looked like on both sides right after it was last exported or
+/// imported, kept in `.haze/state.json`. This is specific to each machine, so
+/// the file is ignored by git.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorldState {
+    pub synced_at: String,
+    pub direction: Direction,
+    /// How the fingerprints were made, which is by contents if the world was
+    /// copied with `--checksum`.
+    #[serde(default)]
+    pub compare: Compare,
+    pub local_fingerprint: String,
+    pub com_mojang_fingerprint: String,
+}
+
+pub type WorldStates = BTreeMap<String, WorldState>;
+
+impl WorldState {
+    /// Records the state of a world that was just copied in `direction`. Each
+    /// side is fingerprinted without the files that are ignored when copying
+    /// from it, since changes to those never make it to the other side.
+    pub fn new(
+        direction: Direction,
+        local: &Path,
+        com_mojang: &Path,
+        compare: Compare,
+        ignores: &WorldIgnores,
+    ) -> io::Result<Self> {
+        Ok(Self {
+            synced_at: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+            direction,
+            compare,
+            local_fingerprint: sync::fingerprint(local, compare, ignores.get(Direction::Export))?,
+            com_mojang_fingerprint: sync::fingerprint(
+                com_mojang,
+                compare,
+                ignores.get(Direction::Import),
+            )?,
+        })
+    }
+
+    /// Returns whether the local world was changed since it was last synced.
+    pub fn local_changed(&self, local: &Path, ignores: &WorldIgnores) -> io::Result<bool> {
+        Ok(
+            sync::fingerprint(local, self.compare, ignores.get(Direction::Export))?
+                != self.local_fingerprint,
+        )
+    }
+
+    /// Returns whether the `com.mojang` world was changed since it was last
+    /// synced.
+    pub fn com_mojang_changed(
+        &self,
+        com_mojang: &Path,
+        ignores: &WorldIgnores,
+    ) -> io::Result<bool> {
+        Ok(
+            sync::fingerprint(com_mojang, self.compare, ignores.get(Direction::Import))?
+                != self.com_mojang_fingerprint,
+        )
+    }
+}
+
+pub fn states_path() -> PathBuf {
+    PathBuf::from(HAZE_DIR).join(STATE_FILE)
+}
+
+pub fn load_states() -> Result<WorldStates> {
+    store::read_json(&states_path())
+}
+
+pub fn save_states(states: &WorldStates) -> Result<()> {
+    store::write_json(&states_path(), states)?;
+    store::ignore(STATE_FILE)
+}
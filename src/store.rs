@@ -0,0 +1,69 @@
+use std::{fs, io, path::Path};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::error::{Error, Result};
+
+/// The directory Haze keeps its own files in, relative to the project root.
+pub const HAZE_DIR: &str = ".haze";
+
+/// Reads a JSON file from the `.haze` directory, falling back to the default
+/// value if it does not exist yet.
+pub fn read_json<T: DeserializeOwned + Default>(path: &Path) -> Result<T> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(T::default()),
+        Err(source) => {
+            return Err(Error::StoreAccessFailure {
+                source,
+                path: path.to_path_buf(),
+            })
+        }
+    };
+
+    serde_json::from_str(&content).map_err(|source| Error::StoreFormat {
+        source,
+        path: path.to_path_buf(),
+    })
+}
+
+/// Adds `name` to `.haze/.gitignore` for files that only make sense on the
+/// machine they were created on.
+pub fn ignore(name: &str) -> Result<()> {
+    let path = Path::new(HAZE_DIR).join(".gitignore");
+    let access_failure = |source| Error::StoreAccessFailure {
+        source,
+        path: path.clone(),
+    };
+
+    let mut content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(source) => return Err(access_failure(source)),
+    };
+    if content.lines().any(|line| line == name) {
+        return Ok(());
+    }
+
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push_str(name);
+    content.push('\n');
+    fs::write(&path, content).map_err(access_failure)
+}
+
+/// Writes a JSON file to the `.haze` directory, creating it if needed.
+pub fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    let access_failure = |source| Error::StoreAccessFailure {
+        source,
+        path: path.to_path_buf(),
+    };
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(access_failure)?;
+    }
+    let mut content = serde_json::to_string_pretty(value).unwrap();
+    content.push('\n');
+    fs::write(path, content).map_err(access_failure)
+}
@@ -0,0 +1,290 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+use xxhash_rust::xxh3::Xxh3;
+
+use crate::{copy, ignore::Ignore};
+
+/// How to tell whether a file differs between two directories.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compare {
+    /// Compare sizes and modification times. This is fast, but misses changes
+    /// that keep both the same.
+    #[default]
+    Metadata,
+    /// Compare sizes and then the contents of files of the same size.
+    Checksum,
+}
+
+impl Compare {
+    /// Picks how to compare files based on whether `--checksum` was passed.
+    pub fn with_checksum(checksum: bool) -> Self {
+        if checksum {
+            Self::Checksum
+        } else {
+            Self::Metadata
+        }
+    }
+}
+
+/// The files that differ between two directories, relative to them.
+#[derive(Default)]
+pub struct Changes {
+    pub added: Vec<PathBuf>,
+    pub changed: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl fmt::Display for Changes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} added, {} changed, {} removed",
+            self.added.len(),
+            self.changed.len(),
+            self.removed.len()
+        )
+    }
+}
+
+/// The files and directories within a directory, relative to it.
+#[derive(Default)]
+struct Listing {
+    files: BTreeMap<PathBuf, fs::Metadata>,
+    dirs: BTreeSet<PathBuf>,
+}
+
+impl Listing {
+    /// Lists everything within `dir` that isn't ignored, which is empty if
+    /// `dir` doesn't exist.
+    fn read(dir: &Path, ignore: &Ignore) -> io::Result<Self> {
+        let mut listing = Self::default();
+        if !dir.exists() {
+            return Ok(listing);
+        }
+
+        let entries = WalkDir::new(dir)
+            .min_depth(1)
+            .into_iter()
+            .filter_entry(|entry| !ignore.is_ignored(entry.path().strip_prefix(dir).unwrap()));
+        for entry in entries {
+            let entry = entry?;
+            let path = entry.path().strip_prefix(dir).unwrap().to_path_buf();
+            if entry.file_type().is_dir() {
+                listing.dirs.insert(path);
+            } else {
+                listing.files.insert(path, entry.metadata()?);
+            }
+        }
+
+        Ok(listing)
+    }
+}
+
+/// Finds the files that would have to be added, changed or removed in `to` to
+/// make it match `from`, leaving out ignored files on both sides.
+pub fn changes(from: &Path, to: &Path, compare: Compare, ignore: &Ignore) -> io::Result<Changes> {
+    let source = Listing::read(from, ignore)?;
+    let target = Listing::read(to, ignore)?;
+
+    let mut changes = Changes::default();
+    let mut candidates = Vec::new();
+    for (path, metadata) in &source.files {
+        match target.files.get(path) {
+            None => changes.added.push(path.clone()),
+            Some(existing) if existing.len() != metadata.len() => {
+                changes.changed.push(path.clone())
+            }
+            Some(existing) => candidates.push((path, metadata, existing)),
+        }
+    }
+    changes.removed = target
+        .files
+        .keys()
+        .filter(|path| !source.files.contains_key(*path))
+        .cloned()
+        .collect();
+
+    let differing = candidates
+        .par_iter()
+        .map(|(path, metadata, existing)| {
+            let differs = match compare {
+                Compare::Metadata => metadata.modified()? != existing.modified()?,
+                Compare::Checksum => checksum(&from.join(path))? != checksum(&to.join(path))?,
+            };
+            Ok(differs.then(|| path.to_path_buf()))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    changes.changed.extend(differing.into_iter().flatten());
+    changes.changed.sort();
+
+    Ok(changes)
+}
+
+/// Which of two directories have files that were modified since they were
+/// last in sync.
+pub struct Modified {
+    pub from: bool,
+    pub to: bool,
+}
+
+/// Works out which of `from` and `to` were modified since they were last in
+/// sync. Since copies keep modification times, the newest file that is still
+/// identical on both sides marks when that was. Anything that differs and is
+/// newer than that was changed on its side, and files that are missing on one
+/// side were deleted there if they are older. Ignored files don't count.
+pub fn modified(from: &Path, to: &Path, ignore: &Ignore) -> io::Result<Modified> {
+    let source = Listing::read(from, ignore)?;
+    let target = Listing::read(to, ignore)?;
+
+    let mut synced_at = None;
+    for (path, metadata) in &source.files {
+        if let Some(existing) = target.files.get(path) {
+            let modified = metadata.modified()?;
+            if metadata.len() == existing.len() && modified == existing.modified()? {
+                synced_at = synced_at.max(Some(modified));
+            }
+        }
+    }
+    let is_newer = |time: SystemTime| synced_at.is_none_or(|synced_at| time > synced_at);
+
+    let mut modified = Modified {
+        from: false,
+        to: false,
+    };
+    for (path, metadata) in &source.files {
+        let time = metadata.modified()?;
+        match target.files.get(path) {
+            None if is_newer(time) => modified.from = true,
+            None => modified.to = true,
+            Some(existing) => {
+                let existing_time = existing.modified()?;
+                if metadata.len() == existing.len() && time == existing_time {
+                    continue;
+                }
+                if !is_newer(time) && !is_newer(existing_time) {
+                    // Both are older than the last sync, so go by whichever
+                    // is newer.
+                    if time > existing_time {
+                        modified.from = true;
+                    } else {
+                        modified.to = true;
+                    }
+                }
+                modified.from |= is_newer(time);
+                modified.to |= is_newer(existing_time);
+            }
+        }
+    }
+    for (path, metadata) in &target.files {
+        if !source.files.contains_key(path) {
+            if is_newer(metadata.modified()?) {
+                modified.to = true;
+            } else {
+                modified.from = true;
+            }
+        }
+    }
+
+    Ok(modified)
+}
+
+/// Fingerprints the names and sizes of the files in `dir`, along with either
+/// their modification times or their contents. Going by modification times is
+/// much cheaper and still catches every time Minecraft saves the world, but
+/// misses changes that keep both the size and the modification time. Ignored
+/// files are left out.
+pub fn fingerprint(dir: &Path, compare: Compare, ignore: &Ignore) -> io::Result<String> {
+    let files: Vec<_> = Listing::read(dir, ignore)?.files.into_iter().collect();
+    let stamps = files
+        .par_iter()
+        .map(|(path, metadata)| match compare {
+            Compare::Metadata => Ok(metadata
+                .modified()?
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos()),
+            Compare::Checksum => checksum(&dir.join(path)).map(u128::from),
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let mut hasher = Xxh3::new();
+    for ((path, metadata), stamp) in files.iter().zip(stamps) {
+        // Use the same separator everywhere so fingerprints are comparable.
+        hasher.update(path.to_string_lossy().replace('\\', "/").as_bytes());
+        hasher.update(&[0]);
+        hasher.update(&metadata.len().to_le_bytes());
+        hasher.update(&stamp.to_le_bytes());
+    }
+
+    Ok(format!("{:016x}", hasher.digest()))
+}
+
+/// Makes `to` match `from` by only copying the files that differ and deleting
+/// the ones that are gone from `from`. `to` is created if it doesn't exist.
+/// Ignored files are neither copied nor deleted.
+pub fn sync(from: &Path, to: &Path, compare: Compare, ignore: &Ignore) -> io::Result<Changes> {
+    let changes = changes(from, to, compare, ignore)?;
+
+    for path in &changes.removed {
+        fs::remove_file(to.join(path))?;
+    }
+    // The files within directories that are gone were just removed, so
+    // removing the deepest directories first leaves each one empty, unless it
+    // still holds ignored files.
+    let source = Listing::read(from, ignore)?;
+    let stale_dirs: Vec<_> = Listing::read(to, ignore)?
+        .dirs
+        .into_iter()
+        .filter(|path| !source.dirs.contains(path))
+        .collect();
+    for path in stale_dirs.iter().rev() {
+        let dir = to.join(path);
+        if fs::read_dir(&dir)?.next().is_none() {
+            fs::remove_dir(dir)?;
+        }
+    }
+
+    fs::create_dir_all(to)?;
+    for path in &source.dirs {
+        fs::create_dir_all(to.join(path))?;
+    }
+    let files: Vec<_> = changes
+        .added
+        .iter()
+        .chain(&changes.changed)
+        .map(|path| (from.join(path), to.join(path)))
+        .collect();
+    copy::copy_files(&files)?;
+
+    Ok(changes)
+}
+
+fn checksum(path: &Path) -> io::Result<u64> {
+    let mut file = File::open(path)?;
+    let mut hasher = Xxh3::new();
+    let mut buffer = [0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(hasher.digest());
+        }
+        hasher.update(&buffer[..read]);
+    }
+}
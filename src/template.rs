@@ -0,0 +1,78 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    error::Result,
+    manifest::{Header, Manifest, Module, Version},
+    metadata::WorldMetadata,
+    store::{self, HAZE_DIR},
+};
+
+pub const MCTEMPLATE_EXTENSION: &str = "mctemplate";
+const TEMPLATES_FILE: &str = "templates.json";
+
+/// The identity of a world template. It is kept in `.haze/templates.json` so
+/// that every build of the same world ends up with the same UUIDs.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateRecord {
+    pub header_uuid: Uuid,
+    pub module_uuid: Uuid,
+    pub version: Version,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_game_version: Option<Version>,
+}
+
+pub type TemplateRecords = BTreeMap<String, TemplateRecord>;
+
+impl Default for TemplateRecord {
+    fn default() -> Self {
+        Self {
+            header_uuid: Uuid::new_v4(),
+            module_uuid: Uuid::new_v4(),
+            version: Version::default(),
+            base_game_version: None,
+        }
+    }
+}
+
+impl TemplateRecord {
+    /// Builds the `manifest.json` for the template of the world at `path`.
+    pub fn manifest(&self, name: &str, path: &Path) -> Manifest {
+        Manifest {
+            format_version: 2,
+            header: Header {
+                name: WorldMetadata::read(path)
+                    .level_name
+                    .unwrap_or_else(|| name.to_string()),
+                description: String::new(),
+                uuid: self.header_uuid,
+                version: self.version,
+                lock_template_options: Some(true),
+                base_game_version: self.base_game_version,
+            },
+            modules: vec![Module {
+                kind: "world_template".to_string(),
+                uuid: self.module_uuid,
+                version: self.version,
+            }],
+        }
+    }
+}
+
+pub fn records_path() -> PathBuf {
+    PathBuf::from(HAZE_DIR).join(TEMPLATES_FILE)
+}
+
+pub fn load_records() -> Result<TemplateRecords> {
+    store::read_json(&records_path())
+}
+
+pub fn save_records(records: &TemplateRecords) -> Result<()> {
+    store::write_json(&records_path(), records)
+}
@@ -23,12 +23,12 @@ pub fn init_logger() {
     env_logger::Builder::from_env(Env::new().filter_or("HAZE_LOG", "info"))
         .format(|buf, record| match record.level() {
             Level::Error => write!(buf, cstr!("<r,s>error:</> {}"), record.args()),
-            Level::Warn => todo!(),
+            Level::Warn => writeln!(buf, cstr!("<y,s>warning:</> {}"), record.args()),
             Level::Info => {
                 writeln!(buf, cstr!("<s,c>info:</> {}"), record.args())
             }
             Level::Debug => writeln!(buf, cstr!("<dim,s>debug:</> {}"), record.args()),
-            Level::Trace => unimplemented!(),
+            Level::Trace => writeln!(buf, cstr!("<dim>trace:</> {}"), record.args()),
         })
         .init();
 }
@@ -0,0 +1,119 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    time::{Duration, Instant},
+};
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::{
+    error::{Error, Result},
+    in_use,
+};
+
+/// How often the watcher checks whether changed worlds can be imported.
+const INTERVAL: Duration = Duration::from_secs(1);
+
+/// Watches worlds in `com.mojang` for Minecraft to be done with them, which
+/// is when a world isn't open anymore and nothing was written to it for the
+/// settle duration.
+pub struct WorldWatcher<'a> {
+    /// The canonical path of each world, since events come with absolute
+    /// paths.
+    dirs: Vec<(&'a String, PathBuf)>,
+    receiver: Receiver<notify::Result<Event>>,
+    // Events stop once the watcher is dropped.
+    _watcher: RecommendedWatcher,
+    settle: Duration,
+    /// The worlds that changed since Minecraft was last done with them, along
+    /// with when they last changed.
+    changed: BTreeMap<&'a String, Instant>,
+}
+
+impl<'a> WorldWatcher<'a> {
+    pub fn new(com_mojang: &Path, names: &'a BTreeSet<String>, settle: Duration) -> Result<Self> {
+        let dirs = names
+            .iter()
+            .map(|name| {
+                let path = com_mojang.join(name);
+                match path.canonicalize() {
+                    Ok(dir) => Ok((name, dir)),
+                    Err(source) => Err(Error::WorldAccessFailure { source, path }),
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher =
+            notify::recommended_watcher(sender).map_err(|source| Error::WatchFailure { source })?;
+        for (_, dir) in &dirs {
+            watcher
+                .watch(dir, RecursiveMode::Recursive)
+                .map_err(|source| Error::WatchFailure { source })?;
+        }
+
+        Ok(Self {
+            dirs,
+            receiver,
+            _watcher: watcher,
+            settle,
+            changed: BTreeMap::new(),
+        })
+    }
+
+    /// Blocks until Minecraft is done with at least one world that changed,
+    /// and returns every world it is done with.
+    pub fn next_settled(&mut self) -> Result<Vec<&'a String>> {
+        loop {
+            match self.receiver.recv_timeout(INTERVAL) {
+                // Reading a world, which Haze does itself, doesn't change it.
+                Ok(Ok(event)) if !matches!(event.kind, EventKind::Access(_)) => self.record(&event),
+                Ok(Ok(_)) | Err(RecvTimeoutError::Timeout) => {}
+                Ok(Err(error)) => log::warn!("failed to watch for changes: {error}"),
+                // The sender lives as long as the watcher, which we own.
+                Err(RecvTimeoutError::Disconnected) => unreachable!(),
+            }
+
+            let settled: Vec<_> = self
+                .changed
+                .iter()
+                .filter(|(name, changed_at)| {
+                    changed_at.elapsed() >= self.settle && !self.is_open(name)
+                })
+                .map(|(name, _)| *name)
+                .collect();
+            if !settled.is_empty() {
+                for name in &settled {
+                    self.changed.remove(name);
+                }
+                return Ok(settled);
+            }
+        }
+    }
+
+    /// Returns whether none of the worlds are open or waiting to settle.
+    pub fn is_idle(&self) -> bool {
+        self.changed.is_empty() && self.dirs.iter().all(|(name, _)| !self.is_open(name))
+    }
+
+    fn record(&mut self, event: &Event) {
+        for path in &event.paths {
+            let Some((name, _)) = self.dirs.iter().find(|(_, dir)| path.starts_with(dir)) else {
+                continue;
+            };
+            if self.changed.insert(name, Instant::now()).is_none() {
+                log::info!("`{name}` changed, waiting for Minecraft to close it..");
+            }
+        }
+    }
+
+    fn is_open(&self, name: &str) -> bool {
+        self.dirs
+            .iter()
+            .find(|(dir_name, _)| *dir_name == name)
+            // Going without changes for the settle duration replaces the
+            // check for recent saves.
+            .is_some_and(|(_, dir)| in_use::check_open(dir).is_some())
+    }
+}
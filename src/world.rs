@@ -1,30 +1,220 @@
 use std::{
-    collections::{HashMap, HashSet},
-    fmt::Write,
+    collections::{BTreeMap, BTreeSet},
+    env,
+    fmt::{self, Write},
     fs,
     path::{Path, PathBuf},
+    time::Duration,
 };
 
 use color_print::cstr;
-use fs_extra::dir::{self, CopyOptions};
+use rayon::prelude::*;
+use serde::Serialize;
 use walkdir::WalkDir;
 
-use crate::error::{Error, NoMatchingWorldsError, Result};
+use crate::{
+    archive::{self, WorldArchive, MCWORLD_EXTENSION},
+    backup::{BackupStore, Side},
+    com_mojang,
+    config::{Config, PacksConfig, WorldConfig},
+    copy,
+    error::{Error, NoMatchingWorldsError, Result},
+    ignore::{Ignore, WorldIgnores},
+    in_use,
+    manifest::Version,
+    metadata::WorldMetadata,
+    packs::{self, PackKind, PackReferences},
+    staging::{self, Leftover, Staging},
+    state::{self, Direction, WorldState, WorldStates},
+    sync::{self, Changes, Compare},
+    template::{self, MCTEMPLATE_EXTENSION},
+    watch::WorldWatcher,
+};
+
+pub type LocalWorldMap = BTreeMap<String, PathBuf>;
+pub type ComMojangWorldSet = BTreeSet<String>;
+
+/// How commands such as `list` and `diff` should print their output.
+#[derive(Clone, Copy)]
+pub enum OutputFormat {
+    /// A colored tree meant for humans.
+    Tree,
+    /// A JSON document meant for tools.
+    Json,
+    /// One tab-separated line per entry meant for scripts.
+    Plain,
+}
 
-pub type LocalWorldMap = HashMap<String, PathBuf>;
-pub type ComMojangWorldSet = HashMap<String, ()>;
+impl OutputFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Tree => "tree",
+            Self::Json => "json",
+            Self::Plain => "plain",
+        }
+    }
+}
+
+/// How `export` and `import` replace worlds.
+#[derive(Clone, Copy, Default)]
+pub struct CopyOptions {
+    /// Replace either all of the worlds or none of them.
+    pub atomic: bool,
+    /// Only copy the files that differ, comparing them this way.
+    pub incremental: Option<Compare>,
+    /// Replace worlds even if they were changed since they were last synced or
+    /// seem to be open in Minecraft.
+    pub force: bool,
+    /// Keep the files in local worlds that Minecraft doesn't write when
+    /// importing them.
+    pub preserve: bool,
+    /// Make exported worlds use the project's packs.
+    pub link_packs: bool,
+    /// Copy the project's packs into exported worlds and make them use them.
+    pub embed_packs: bool,
+    /// What to do with references to the project's packs when importing
+    /// worlds, instead of what is set for each world.
+    pub pack_references: Option<PackReferences>,
+    /// The worlds were watched until nothing was written to them for a while,
+    /// so recent saves don't make them count as open.
+    pub settled: bool,
+}
+
+/// The document printed by `list --format json`.
+#[derive(Serialize)]
+struct ListDocument {
+    worlds: Vec<ListedWorld>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ListedWorld {
+    name: String,
+    local_path: Option<PathBuf>,
+    in_com_mojang: bool,
+    com_mojang_path: PathBuf,
+}
+
+/// The document printed by `diff --format json`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DiffDocument {
+    name: String,
+    local_path: PathBuf,
+    com_mojang_path: PathBuf,
+    added: usize,
+    changed: usize,
+    removed: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    files: Option<Vec<DiffedFile>>,
+}
+
+/// A file that differs between a local world and its `com.mojang` copy.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DiffedFile {
+    path: PathBuf,
+    status: DiffStatus,
+    local_size: Option<u64>,
+    com_mojang_size: Option<u64>,
+}
+
+/// How a file in `com.mojang` differs from the local one, which is what
+/// `import` would change.
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum DiffStatus {
+    Added,
+    Changed,
+    Removed,
+}
+
+impl DiffStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Added => "added",
+            Self::Changed => "changed",
+            Self::Removed => "removed",
+        }
+    }
+}
+
+/// Whether a world's local and `com.mojang` copies are in sync.
+#[derive(Clone, Copy)]
+enum WorldStatus {
+    OnlyLocal,
+    OnlyComMojang,
+    Identical,
+    ModifiedInGame,
+    ModifiedLocally,
+    Diverged,
+}
+
+impl WorldStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::OnlyLocal => "only-local",
+            Self::OnlyComMojang => "only-com-mojang",
+            Self::Identical => "identical",
+            Self::ModifiedInGame => "modified-in-game",
+            Self::ModifiedLocally => "modified-locally",
+            Self::Diverged => "diverged",
+        }
+    }
+}
+
+impl fmt::Display for WorldStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OnlyLocal => write!(f, "only local"),
+            Self::OnlyComMojang => write!(f, "only in com.mojang"),
+            Self::Identical => write!(f, "identical"),
+            Self::ModifiedInGame => write!(f, "modified in game"),
+            Self::ModifiedLocally => write!(f, "modified locally"),
+            Self::Diverged => write!(f, "modified both in game and locally"),
+        }
+    }
+}
+
+/// The document printed by `status --format json`.
+#[derive(Serialize)]
+struct StatusDocument {
+    worlds: Vec<StatusEntry>,
+}
+
+#[derive(Serialize)]
+struct StatusEntry {
+    name: String,
+    status: &'static str,
+}
 
 /// Holds info about local and `com.mojang` worlds.
 pub struct WorldManager {
     local_worlds: LocalWorldMap,
     com_mojang_worlds: ComMojangWorldSet,
+    /// The directory within `com.mojang` that holds the worlds.
     com_mojang: PathBuf,
+    com_mojang_root: PathBuf,
+    backups: BackupStore,
+    /// The files to ignore in every world, as set in `config.json`.
+    ignores: WorldIgnores,
+    /// The files that `import --preserve` keeps in local worlds.
+    preserved: Ignore,
+    packs: PacksConfig,
+    world_configs: BTreeMap<String, WorldConfig>,
+    /// The name development packs are exported as.
+    project_name: String,
 }
 
 impl WorldManager {
-    pub fn new(patterns: Vec<String>, com_mojang: PathBuf) -> Result<Self> {
-        let local_worlds = patterns
-            .clone()
+    pub fn new(config: Config, com_mojang_root: PathBuf) -> Result<Self> {
+        let com_mojang = com_mojang::worlds_dir(&com_mojang_root);
+        let backups = BackupStore::new(&config.haze.backups);
+        let ignores = WorldIgnores::from_config(&config.haze.ignore)?;
+        let preserved = Ignore::preserved(config.haze.preserve.as_deref())?;
+
+        let paths = config
+            .worlds
             .into_iter()
             .map(|pattern| {
                 glob::glob(&pattern).map_err(|source| Error::InvalidWorldGlob { source, pattern })
@@ -32,13 +222,49 @@ impl WorldManager {
             .collect::<Result<Vec<_>>>()?
             .into_iter()
             .flatten()
+            .map(|path| {
+                path.map_err(|e| Error::WorldAccessFailure {
+                    path: e.path().to_path_buf(),
+                    source: e.into_error(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // Clean up after previous runs before looking at the worlds, since
+        // this may bring back worlds that were moved aside.
+        let dirs: BTreeSet<_> = paths
+            .iter()
+            .filter_map(|path| path.parent())
+            .chain([com_mojang.as_path()])
+            .collect();
+        for kind in PackKind::ALL {
+            clean_up_pack_leftovers(&com_mojang_root.join(kind.development_dir()))?;
+        }
+        let mut recovered = Vec::new();
+        for dir in dirs {
+            let side = if dir == com_mojang {
+                Side::ComMojang
+            } else {
+                Side::Local
+            };
+            recovered.extend(clean_up_leftovers(dir, side, &backups)?);
+        }
+
+        let local_worlds = paths
+            .iter()
+            .filter(|path| !staging::is_leftover(path))
+            .cloned()
+            // Recovered worlds only count if the patterns matched them before
+            // they were moved aside.
+            .chain(
+                recovered
+                    .into_iter()
+                    .filter(|(old_path, _)| paths.contains(old_path))
+                    .map(|(_, path)| path),
+            )
             .try_fold(
-                HashMap::new(),
+                BTreeMap::new(),
                 |mut worlds, path| -> Result<LocalWorldMap> {
-                    let path = path.map_err(|e| Error::WorldAccessFailure {
-                        path: e.path().to_path_buf(),
-                        source: e.into_error(),
-                    })?;
                     let name = world_name_from_path(&path);
                     match worlds.get(&name) {
                         Some(old_path) => {
@@ -58,8 +284,8 @@ impl WorldManager {
             .max_depth(1)
             .into_iter()
             .filter_map(|entry| match entry {
-                Ok(entry) if entry.file_type().is_dir() => {
-                    Some(Ok((world_name_from_path(entry.path()), ())))
+                Ok(entry) if entry.file_type().is_dir() && !staging::is_leftover(entry.path()) => {
+                    Some(Ok(world_name_from_path(entry.path())))
                 }
                 Ok(_) => None,
                 Err(err) => match err.io_error() {
@@ -76,89 +302,968 @@ impl WorldManager {
             local_worlds,
             com_mojang_worlds,
             com_mojang,
+            com_mojang_root,
+            backups,
+            ignores,
+            preserved,
+            packs: config.packs,
+            world_configs: config.haze.worlds,
+            project_name: config.name.unwrap_or_else(|| {
+                env::current_dir()
+                    .ok()
+                    .and_then(|dir| Some(dir.file_name()?.to_string_lossy().into_owned()))
+                    .unwrap_or_else(|| "project".to_string())
+            }),
         })
     }
 
-    /// Sequentially exports the given local worlds to `com.mojang`.
-    pub fn export(mut self, names: Vec<String>, overwrite: bool) -> Result<()> {
-        let names = HashSet::<String>::from_iter(names);
-        let names_not_found: Vec<_> = names
-            .iter()
-            .filter(|&name| (!self.local_worlds.contains_key(name)))
-            .cloned()
-            .collect();
+    /// The files to ignore when copying the world `name`, which the local
+    /// copy of the world can add to with its `.hazeignore`. Copies of the
+    /// project's packs embedded in the `com.mojang` world are never imported,
+    /// so embedding them leaves the local world as it is.
+    fn world_ignores(&self, name: &str) -> Result<WorldIgnores> {
+        let ignores = match self.local_worlds.get(name) {
+            Some(local) => self.ignores.with_file(local)?,
+            None => self.ignores.clone(),
+        };
+
+        if !self.com_mojang_worlds.contains(name) {
+            return Ok(ignores);
+        }
+        // Commands that don't use the packs shouldn't fail because of them.
+        let embedded = packs::load_project_packs(&self.packs)
+            .and_then(|packs| packs::embedded_copies(&self.com_mojang.join(name), &packs));
+        match embedded {
+            Ok(embedded) => ignores.skipping_on_import(&embedded),
+            Err(Error::NoProjectPacks) => Ok(ignores),
+            Err(error) => {
+                log::debug!("not looking for packs embedded in `{name}`: {error}");
+                Ok(ignores)
+            }
+        }
+    }
+
+    /// Copies a world into a staging directory next to `to` with `fill`, and
+    /// only replaces `to` once that has succeeded. The replaced world is
+    /// backed up.
+    fn replace_world(
+        &self,
+        name: &str,
+        side: Side,
+        to: &Path,
+        fill: impl FnOnce(&Path) -> Result<()>,
+    ) -> Result<()> {
+        let staging = Staging::new(to)?;
+        fill(staging.path())?;
+        self.commit_world(name, side, staging)
+    }
 
-        if !names_not_found.is_empty() {
-            return Err(Error::NoMatchingWorlds(NoMatchingWorldsError {
-                names: names_not_found,
-            }));
+    /// Swaps a staged world into place and backs up the `side` copy of the
+    /// world it replaced.
+    fn commit_world(&self, name: &str, side: Side, staging: Staging) -> Result<()> {
+        let to = staging.target().to_path_buf();
+        if let Some(old) = staging.commit()?.old {
+            discard_world(&self.backups, name, side, &old, &to)?;
         }
 
+        Ok(())
+    }
+
+    /// Exports the given local worlds to `com.mojang`.
+    pub fn export(&self, names: Vec<String>, overwrite: bool, options: CopyOptions) -> Result<()> {
+        let names = BTreeSet::<String>::from_iter(names);
+        ensure_all_found(&names, |name| self.local_worlds.contains_key(name))?;
+
+        let states = state::load_states()?;
+        let mut worlds = Vec::new();
         for name in names {
             // We've already checked that `name` *does* exist in `local_worlds`.
-            let from = self.local_worlds.remove(&name).unwrap();
+            let from = self.local_worlds[&name].clone();
             let to = self.com_mojang.join(&name);
 
-            match (self.com_mojang_worlds.contains_key(&name), overwrite) {
-                // 1. Target world does exist and we can delete it before copying.
-                (true, true) => {
-                    fs::remove_dir_all(&to).map_err(|source| Error::WorldAccessFailure {
-                        source,
-                        path: to.to_path_buf(),
+            if self.com_mojang_worlds.contains(&name) {
+                // The target world does exist, but we cannot overwrite it.
+                if !overwrite {
+                    return Err(Error::ExportWithoutOverwriteAllowed { name });
+                }
+                check_in_use(&name, &to, options)?;
+                if let Some(state) = states.get(&name) {
+                    let ignores = self.world_ignores(&name)?;
+                    let changed = state.com_mojang_changed(&to, &ignores).map_err(|source| {
+                        Error::WorldAccessFailure {
+                            source,
+                            path: to.clone(),
+                        }
+                    })?;
+                    check_clobber(changed, &to, options.force, || Error::ExportWouldClobber {
+                        name: name.clone(),
                     })?;
-                    copy_world(&from, &to)?;
                 }
-                // 2. Target world does exist, but we cannot overwrite it.
-                (true, false) => return Err(Error::ExportWithoutOverwriteAllowed { name }),
-                // 3. Target world does not exist, we can copy normally.
-                _ => copy_world(&from, &to)?,
             }
 
-            log::info!("exported `{}` to `{}`", from.display(), to.display());
+            worlds.push((name, from, to));
+        }
+
+        self.copy_worlds(&worlds, options, Direction::Export)
+    }
+
+    /// What importing the world `name` does with its references to the
+    /// project's packs, which `--pack-references` overrides for every world.
+    fn pack_references(&self, name: &str, options: CopyOptions) -> PackReferences {
+        options
+            .pack_references
+            .or_else(|| self.world_configs.get(name)?.pack_references)
+            .unwrap_or_default()
+    }
+
+    /// Checks the packs that the given local worlds use, or all of them if
+    /// none are given, against the project's packs and the ones embedded in
+    /// each world. Every problem is reported before failing.
+    pub fn check(self, names: Vec<String>) -> Result<()> {
+        let names = if names.is_empty() {
+            self.local_worlds.keys().cloned().collect()
+        } else {
+            BTreeSet::from_iter(names)
+        };
+        ensure_all_found(&names, |name| self.local_worlds.contains_key(name))?;
+        let packs = packs::load_project_packs(&self.packs)?;
+
+        let mut problems = 0;
+        for name in &names {
+            let found = packs::check(&self.local_worlds[name], &packs)?;
+            for problem in &found {
+                log::warn!("`{name}` {problem}");
+            }
+            problems += found.len();
+        }
+        if problems > 0 {
+            return Err(Error::PackProblems { problems });
+        }
+
+        log::info!(
+            "checked the packs of {} {}, found no problems",
+            names.len(),
+            if names.len() == 1 { "world" } else { "worlds" }
+        );
+        Ok(())
+    }
+
+    /// Copies the project's packs into the `development_behavior_packs` and
+    /// `development_resource_packs` directories of `com.mojang`, or links them
+    /// there so that changes show up without exporting them again. Copies are
+    /// only replaced with links if `force` is set.
+    pub fn export_packs(&self, symlink: bool, force: bool) -> Result<()> {
+        for pack in packs::load_project_packs(&self.packs)? {
+            let to = pack.development_path(&self.com_mojang_root, &self.project_name);
+            packs::deploy(&pack, &to, symlink, force)?;
+            log::info!(
+                "{} `{}` to `{}`",
+                if symlink { "linked" } else { "exported" },
+                pack.path.display(),
+                to.display()
+            );
         }
 
         Ok(())
     }
 
-    /// Sequentially imports the given worlds from `com.mojang` and stores them
-    /// locally.
-    pub fn import(mut self, names: Vec<String>) -> Result<()> {
-        let names = HashSet::<String>::from_iter(names);
-        let names_not_found: Vec<_> = names
+    /// Imports the given worlds from `com.mojang` and stores them locally.
+    pub fn import(&self, names: Vec<String>, options: CopyOptions) -> Result<()> {
+        let names = BTreeSet::<String>::from_iter(names);
+        ensure_all_found(&names, |name| self.com_mojang_worlds.contains(name))?;
+
+        let states = state::load_states()?;
+        let worlds = names
+            .into_iter()
+            .map(|name| self.prepare_import(&states, name, options))
+            .collect::<Result<Vec<_>>>()?;
+
+        self.copy_worlds(&worlds, options, Direction::Import)
+    }
+
+    /// Checks that the `com.mojang` world `name` can be imported, and returns
+    /// where it would be copied from and to.
+    fn prepare_import(
+        &self,
+        states: &WorldStates,
+        name: String,
+        options: CopyOptions,
+    ) -> Result<(String, PathBuf, PathBuf)> {
+        let Some(to) = self.local_worlds.get(&name).cloned() else {
+            return Err(Error::ImportWithoutLocalMatch { name });
+        };
+        let from = self.com_mojang.join(&name);
+
+        check_in_use(&name, &from, options)?;
+        if let Some(state) = states.get(&name) {
+            let changed = state
+                .local_changed(&to, &self.world_ignores(&name)?)
+                .map_err(|source| Error::WorldAccessFailure {
+                    source,
+                    path: to.clone(),
+                })?;
+            check_clobber(changed, &to, options.force, || Error::ImportWouldClobber {
+                name: name.clone(),
+            })?;
+        }
+
+        Ok((name, from, to))
+    }
+
+    /// Watches the given worlds in `com.mojang` and imports each one once
+    /// Minecraft is done with it, which is when it isn't open anymore and
+    /// nothing was written to it for `settle`. Runs until interrupted.
+    pub fn watch(self, names: Vec<String>, settle: Duration) -> Result<()> {
+        let names = BTreeSet::<String>::from_iter(names);
+        ensure_all_found(&names, |name| self.com_mojang_worlds.contains(name))?;
+        if let Some(name) = names
             .iter()
-            .filter(|&name| (!self.com_mojang_worlds.contains_key(name)))
-            .cloned()
+            .find(|name| !self.local_worlds.contains_key(*name))
+        {
+            return Err(Error::ImportWithoutLocalMatch { name: name.clone() });
+        }
+
+        let mut watcher = WorldWatcher::new(&self.com_mojang, &names, settle)?;
+        log::info!(
+            "watching {} for changes, press Ctrl-C to stop..",
+            names
+                .iter()
+                .map(|name| format!("`{}`", self.com_mojang.join(name).display()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+
+        loop {
+            for name in watcher.next_settled()? {
+                let options = CopyOptions {
+                    settled: true,
+                    ..CopyOptions::default()
+                };
+                let imported = state::load_states()
+                    .and_then(|states| self.prepare_import(&states, name.clone(), options))
+                    .and_then(|world| self.copy_worlds(&[world], options, Direction::Import));
+                // Keep watching, so the other worlds are still imported.
+                if let Err(error) = imported {
+                    log::error!("failed to import `{name}`: {error}");
+                }
+            }
+        }
+    }
+
+    /// Copies each `(name, from, to)` world in the given direction, and
+    /// records the state of every world that made it into place.
+    fn copy_worlds(
+        &self,
+        worlds: &[(String, PathBuf, PathBuf)],
+        options: CopyOptions,
+        direction: Direction,
+    ) -> Result<()> {
+        let ignores = worlds
+            .iter()
+            .map(|(name, _, _)| self.world_ignores(name))
+            .collect::<Result<Vec<_>>>()?;
+        // Preserved files are only left alone while copying, so they still
+        // count towards the recorded state.
+        let copy_ignores: Vec<_> = match direction {
+            Direction::Import if options.preserve => ignores
+                .iter()
+                .map(|ignores| ignores.clone().preserving(&self.preserved))
+                .collect(),
+            _ => ignores.clone(),
+        };
+        let pack_references: BTreeMap<_, _> = worlds
+            .iter()
+            .map(|(name, _, _)| (name.as_str(), self.pack_references(name, options)))
+            .collect();
+        let needs_packs = match direction {
+            Direction::Export => options.link_packs || options.embed_packs,
+            Direction::Import => pack_references
+                .values()
+                .any(|mode| !matches!(mode, PackReferences::Keep)),
+        };
+        let packs = if needs_packs {
+            packs::load_project_packs(&self.packs)?
+        } else {
+            Vec::new()
+        };
+        // Packs are only linked or embedded in `com.mojang` and only cleaned up
+        // locally, so none of this changes the world that was copied.
+        let finish = |name: &str, world: &Path| match direction {
+            Direction::Export if options.embed_packs => {
+                packs::embed(world, &packs, &self.project_name)
+            }
+            Direction::Export if options.link_packs => packs::link(world, &packs),
+            Direction::Export => Ok(()),
+            Direction::Import => packs::clean_references(world, &packs, pack_references[name]),
+        };
+
+        let mut states = state::load_states()?;
+        let result = self.replace_worlds(
+            worlds,
+            &copy_ignores,
+            direction,
+            options,
+            finish,
+            |index, changes| {
+                let (name, from, to) = &worlds[index];
+                let verb = direction.verb();
+                match changes {
+                    Some(changes) if changes.is_empty() => {
+                        log::info!("`{}` is already up to date", to.display());
+                    }
+                    Some(changes) => log::info!(
+                        "{verb} `{}` to `{}` ({changes})",
+                        from.display(),
+                        to.display()
+                    ),
+                    None => log::info!("{verb} `{}` to `{}`", from.display(), to.display()),
+                }
+
+                let (local, com_mojang) = match direction {
+                    Direction::Export => (from, to),
+                    Direction::Import => (to, from),
+                };
+                let compare = options.incremental.unwrap_or_default();
+                // Packs may have just been embedded in the `com.mojang` world,
+                // which changes what is ignored in it.
+                let ignores = match self.world_ignores(name) {
+                    Ok(ignores) => ignores,
+                    Err(error) => {
+                        log::debug!("not recording the state of `{name}`: {error}");
+                        return;
+                    }
+                };
+                match WorldState::new(direction, local, com_mojang, compare, &ignores) {
+                    Ok(state) => {
+                        states.insert(name.clone(), state);
+                    }
+                    Err(error) => log::debug!("not recording the state of `{name}`: {error}"),
+                }
+            },
+        );
+
+        state::save_states(&states)?;
+        result
+    }
+
+    /// Replaces each `(name, from, to)` world, calling `copied` with its index
+    /// in order after each one is in place, along with the changes that were
+    /// made if the copy was incremental. A world failing to copy doesn't stop
+    /// the others unless the copy is atomic, in which case none of them are
+    /// replaced. Ignored files are left out, and the ones already in `to` are
+    /// kept. Each copy is handed to `finish` before it is put in place.
+    fn replace_worlds(
+        &self,
+        worlds: &[(String, PathBuf, PathBuf)],
+        ignores: &[WorldIgnores],
+        direction: Direction,
+        options: CopyOptions,
+        finish: impl Fn(&str, &Path) -> Result<()> + Sync,
+        mut copied: impl FnMut(usize, Option<Changes>),
+    ) -> Result<()> {
+        if let Some(compare) = options.incremental {
+            let synced: Vec<_> = worlds
+                .par_iter()
+                .zip(ignores)
+                .map(|((name, from, to), ignores)| {
+                    // Incremental copies happen in place, so they are finished
+                    // where they are.
+                    let changes = sync_world(from, to, compare, ignores.get(direction))?;
+                    finish(name, to)?;
+                    Ok(changes)
+                })
+                .collect();
+
+            let mut errors = Vec::new();
+            for (index, synced) in synced.into_iter().enumerate() {
+                match synced {
+                    Ok(changes) => copied(index, Some(changes)),
+                    Err(error) => errors.push(error),
+                }
+            }
+
+            return batch_result(worlds.len(), errors);
+        }
+
+        // Copying is by far the slowest part, so every world is staged at
+        // once. Swapping them into place is done one by one afterwards to keep
+        // the output in order.
+        let staged: Vec<_> = worlds
+            .par_iter()
+            .zip(ignores)
+            .map(|((name, from, to), ignores)| {
+                let ignore = ignores.get(direction);
+                let staging = Staging::new(to)?;
+                copy_world(from, staging.path(), ignore)?;
+                copy::copy_ignored(to, staging.path(), ignore).map_err(|source| {
+                    Error::WorldCopyFailure {
+                        source,
+                        from: to.clone(),
+                        to: staging.path().to_path_buf(),
+                    }
+                })?;
+                finish(name, staging.path())?;
+                Ok(staging)
+            })
             .collect();
 
-        if !names_not_found.is_empty() {
-            return Err(Error::NoMatchingWorlds(NoMatchingWorldsError {
-                names: names_not_found,
-            }));
+        if !options.atomic {
+            let mut errors = Vec::new();
+            for (index, ((name, _, _), staged)) in worlds.iter().zip(staged).enumerate() {
+                match staged.and_then(|staging| {
+                    self.commit_world(name, Side::target_of(direction), staging)
+                }) {
+                    Ok(()) => copied(index, None),
+                    Err(error) => errors.push(error),
+                }
+            }
+
+            return batch_result(worlds.len(), errors);
+        }
+
+        let mut stagings = Vec::new();
+        let mut outcomes = Vec::new();
+        let mut errors = Vec::new();
+        for staged in staged {
+            match staged {
+                Ok(staging) => {
+                    stagings.push(staging);
+                    outcomes.push(BatchOutcome::Discarded);
+                }
+                Err(error) => {
+                    errors.push(error);
+                    outcomes.push(BatchOutcome::Failed);
+                }
+            }
+        }
+        if !errors.is_empty() {
+            // Dropping the stagings deletes them.
+            drop(stagings);
+            report_batch(worlds, outcomes);
+            return batch_result(worlds.len(), errors);
+        }
+
+        let mut commits = Vec::new();
+        for staging in stagings {
+            let error = match staging.commit() {
+                Ok(commit) => {
+                    commits.push(commit);
+                    continue;
+                }
+                Err(error) => error,
+            };
+
+            // Revert in reverse order, which is the same as going back in
+            // time.
+            let mut outcomes: Vec<_> = commits
+                .iter()
+                .rev()
+                .map(|commit| match commit.revert() {
+                    Ok(()) => BatchOutcome::RolledBack,
+                    Err(error) => BatchOutcome::RollbackFailed(error),
+                })
+                .collect();
+            outcomes.reverse();
+            outcomes.push(BatchOutcome::Failed);
+            // The remaining stagings are deleted once we return.
+            outcomes.resize_with(worlds.len(), || BatchOutcome::Discarded);
+            report_batch(worlds, outcomes);
+            return Err(error);
+        }
+
+        // Only back up the replaced worlds once nothing needs to be rolled
+        // back anymore.
+        for (index, ((name, _, to), commit)) in worlds.iter().zip(commits).enumerate() {
+            if let Some(old) = commit.old {
+                discard_world(&self.backups, name, Side::target_of(direction), &old, to)?;
+            }
+            copied(index, None);
+        }
+
+        Ok(())
+    }
+
+    /// Imports a world from an `.mcworld` or `.zip` archive and stores it in
+    /// the local world matching the given name. The replaced world is backed
+    /// up rather than checked for changes, and no state is recorded, since the
+    /// world has nothing to do with its copy in `com.mojang`. Keeping the state
+    /// of the last sync makes the world count as modified locally.
+    pub fn import_archive(mut self, archive: PathBuf, names: Vec<String>) -> Result<()> {
+        let [name] = <[String; 1]>::try_from(names)
+            .map_err(|names| Error::ArchiveImportWithMultipleWorlds { count: names.len() })?;
+        let Some(to) = self.local_worlds.remove(&name) else {
+            return Err(Error::ImportWithoutLocalMatch { name });
+        };
+
+        let world_archive = WorldArchive::open(&archive)?;
+        self.replace_world(&name, Side::Local, &to, |staging| {
+            world_archive.unpack(staging)
+        })?;
+
+        log::info!("imported `{}` to `{}`", archive.display(), to.display());
+
+        Ok(())
+    }
+
+    /// Exports the given worlds, waits while they are played in Minecraft, and
+    /// imports the ones that were played once Minecraft is done with all of
+    /// them.
+    pub fn session(
+        &self,
+        names: Vec<String>,
+        settle: Duration,
+        options: CopyOptions,
+    ) -> Result<()> {
+        let names = BTreeSet::<String>::from_iter(names);
+        self.export(names.iter().cloned().collect(), true, options)?;
+
+        let mut watcher = WorldWatcher::new(&self.com_mojang, &names, settle)?;
+        log::info!("play the worlds in Minecraft, they will be imported once you close them..");
+        let mut played = BTreeSet::new();
+        loop {
+            played.extend(watcher.next_settled()?);
+            if watcher.is_idle() {
+                break;
+            }
         }
 
+        // Work out what changed before the import makes both sides the same.
+        let changes = played
+            .iter()
+            .map(|&name| {
+                let ignores = self.world_ignores(name)?;
+                let changes = sync::changes(
+                    &self.com_mojang.join(name),
+                    &self.local_worlds[name],
+                    Compare::Metadata,
+                    ignores.get(Direction::Import),
+                )
+                .map_err(|source| Error::WorldDiffFailure {
+                    source,
+                    name: name.clone(),
+                })?;
+                Ok((name, changes))
+            })
+            .collect::<Result<BTreeMap<_, _>>>()?;
+        self.import(
+            played.into_iter().cloned().collect(),
+            CopyOptions {
+                settled: true,
+                ..options
+            },
+        )?;
+
+        let mut output = String::new();
+        write_tree(
+            &mut output,
+            names.iter().map(|name| {
+                format!(
+                    cstr!("{} <dim>({})</>"),
+                    name,
+                    changes
+                        .get(name)
+                        .map_or("not played".to_string(), |changes| changes.to_string())
+                )
+            }),
+        );
+        log::info!("summary of the session..\n{output}");
+
+        Ok(())
+    }
+
+    /// Replaces a world with one of its backups. The world being replaced is
+    /// backed up as well, so a restore can be undone. Like exports, restores
+    /// to `com.mojang` refuse to replace a world that is open unless `force`
+    /// is set.
+    pub fn restore(
+        self,
+        name: String,
+        timestamp: Option<String>,
+        target: Side,
+        force: bool,
+    ) -> Result<()> {
+        let snapshot = self.backups.find(&name, target, timestamp.as_deref())?;
+        let to = match target {
+            Side::Local => match self.local_worlds.get(&name) {
+                Some(path) => path.clone(),
+                None => return Err(Error::RestoreWithoutLocalMatch { name }),
+            },
+            Side::ComMojang => {
+                let to = self.com_mojang.join(&name);
+                if self.com_mojang_worlds.contains(&name) {
+                    let options = CopyOptions {
+                        force,
+                        ..CopyOptions::default()
+                    };
+                    check_in_use(&name, &to, options)?;
+                }
+                to
+            }
+        };
+
+        self.replace_world(&name, target, &to, |staging| {
+            copy_world(&snapshot.path, staging, &Ignore::default())
+        })?;
+
+        log::info!(
+            "restored `{}` from the backup at {}",
+            to.display(),
+            snapshot.time()
+        );
+
+        Ok(())
+    }
+
+    /// Lists the backups of a world from oldest to newest.
+    pub fn backups(self, name: String) -> Result<()> {
+        let snapshots = self.backups.snapshots(&name)?;
+        if snapshots.is_empty() {
+            return Err(Error::NoBackups { name });
+        }
+
+        let mut output = String::new();
+        write_tree(
+            &mut output,
+            snapshots.iter().map(|snapshot| {
+                format!(
+                    cstr!("{} <dim>({}, {})</>"),
+                    snapshot.time(),
+                    snapshot.side.as_str(),
+                    format_size(snapshot.size())
+                )
+            }),
+        );
+
+        log::info!("listing backups of `{name}`..\n{output}");
+
+        Ok(())
+    }
+
+    /// Packages the given local worlds as `.mcworld` archives in `out_dir`.
+    pub fn pack(self, names: Vec<String>, out_dir: PathBuf, embed_packs: bool) -> Result<()> {
+        let names = BTreeSet::<String>::from_iter(names);
+        ensure_all_found(&names, |name| self.local_worlds.contains_key(name))?;
+        let packs = if embed_packs {
+            packs::load_project_packs(&self.packs)?
+        } else {
+            Vec::new()
+        };
+
+        fs::create_dir_all(&out_dir).map_err(|source| Error::WorldAccessFailure {
+            source,
+            path: out_dir.clone(),
+        })?;
+
         for name in names {
-            // We've already checked that `name` *does* exist in `com.mojang`.
-            let (from, _) = self.com_mojang_worlds.remove_entry(&name).unwrap();
-            let from = self.com_mojang.join(from);
-            let to = self
-                .local_worlds
-                .remove(&name)
-                .ok_or_else(|| Error::ImportWithoutLocalMatch { name })?;
-
-            fs::remove_dir_all(&to).map_err(|source| Error::WorldAccessFailure {
-                source,
-                path: to.to_path_buf(),
-            })?;
-            copy_world(&from, &to)?;
+            let from = &self.local_worlds[&name];
+            let to = out_dir.join(format!("{name}.{MCWORLD_EXTENSION}"));
+            if embed_packs {
+                // The packs are embedded in a copy of the world, which is
+                // deleted once it has been packed.
+                let staging = Staging::new(&out_dir.join(&name))?;
+                copy_world(from, staging.path(), &Ignore::default())?;
+                packs::embed(staging.path(), &packs, &self.project_name)?;
+                archive::pack_world(staging.path(), &to, &[])?;
+            } else {
+                archive::pack_world(from, &to, &[])?;
+            }
+
+            log::info!("packed `{}` into `{}`", from.display(), to.display());
+        }
+
+        Ok(())
+    }
+
+    /// Packages the given local worlds as `.mctemplate` world templates in
+    /// `out_dir`, generating a `manifest.json` for each of them.
+    pub fn template(
+        self,
+        names: Vec<String>,
+        out_dir: PathBuf,
+        version: Option<Version>,
+        base_game_version: Option<Version>,
+    ) -> Result<()> {
+        let names = BTreeSet::<String>::from_iter(names);
+        ensure_all_found(&names, |name| self.local_worlds.contains_key(name))?;
+
+        fs::create_dir_all(&out_dir).map_err(|source| Error::WorldAccessFailure {
+            source,
+            path: out_dir.clone(),
+        })?;
+
+        let mut records = template::load_records()?;
+        for name in names {
+            let from = &self.local_worlds[&name];
+            let to = out_dir.join(format!("{name}.{MCTEMPLATE_EXTENSION}"));
 
-            log::info!("imported `{}` to `{}`", from.display(), to.display());
+            let record = records.entry(name.clone()).or_default();
+            if let Some(version) = version {
+                record.version = version;
+            }
+            if base_game_version.is_some() {
+                record.base_game_version = base_game_version;
+            }
+            let manifest = record.manifest(&name, from);
+            let content = serde_json::to_vec_pretty(&manifest).unwrap();
+            archive::pack_world(from, &to, &[("manifest.json", &content)])?;
+            template::save_records(&records)?;
+
+            log::info!(
+                "packed `{}` into `{}` (version {})",
+                from.display(),
+                to.display(),
+                manifest.header.version
+            );
         }
 
         Ok(())
     }
 
     /// List worlds stored locally and in `com.mojang`.
-    pub fn list(self) -> Result<()> {
+    pub fn list(self, format: OutputFormat) -> Result<()> {
+        match format {
+            OutputFormat::Tree => self.list_tree(),
+            OutputFormat::Json => {
+                let document = ListDocument {
+                    worlds: self.listed_worlds(),
+                };
+                println!("{}", serde_json::to_string_pretty(&document).unwrap());
+            }
+            OutputFormat::Plain => {
+                for world in self.listed_worlds() {
+                    println!(
+                        "{}\t{}\t{}",
+                        world.name,
+                        world
+                            .local_path
+                            .map_or("-".to_string(), |path| path.display().to_string()),
+                        if world.in_com_mojang {
+                            world.com_mojang_path.display().to_string()
+                        } else {
+                            "-".to_string()
+                        }
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compares a local world with its copy in `com.mojang`, showing the files
+    /// that were added, changed or removed in `com.mojang`.
+    pub fn diff(
+        self,
+        name: String,
+        stat: bool,
+        compare: Compare,
+        format: OutputFormat,
+    ) -> Result<()> {
+        ensure_all_found(&BTreeSet::from([name.clone()]), |name| {
+            self.com_mojang_worlds.contains(name)
+        })?;
+        let Some(local) = self.local_worlds.get(&name) else {
+            return Err(Error::DiffWithoutLocalMatch { name });
+        };
+        let com_mojang = self.com_mojang.join(&name);
+
+        let ignores = self.world_ignores(&name)?;
+        let ignore = ignores.get(Direction::Import);
+        let changes = sync::changes(&com_mojang, local, compare, ignore).map_err(|source| {
+            Error::WorldDiffFailure {
+                source,
+                name: name.clone(),
+            }
+        })?;
+        let size = |path: PathBuf| fs::metadata(path).ok().map(|metadata| metadata.len());
+        let mut files: Vec<_> = [
+            (DiffStatus::Added, &changes.added),
+            (DiffStatus::Changed, &changes.changed),
+            (DiffStatus::Removed, &changes.removed),
+        ]
+        .into_iter()
+        .flat_map(|(status, paths)| paths.iter().map(move |path| (status, path)))
+        .map(|(status, path)| DiffedFile {
+            path: path.clone(),
+            status,
+            local_size: size(local.join(path)),
+            com_mojang_size: size(com_mojang.join(path)),
+        })
+        .collect();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        match format {
+            OutputFormat::Tree => {
+                if changes.is_empty() {
+                    log::info!(
+                        "`{}` and `{}` are identical",
+                        local.display(),
+                        com_mojang.display()
+                    );
+                    return Ok(());
+                }
+                if !stat {
+                    log::info!(
+                        "comparing `{}` with `{}`..\n{}",
+                        local.display(),
+                        com_mojang.display(),
+                        diff_tree(&files)
+                    );
+                }
+                log::info!("{changes}");
+            }
+            OutputFormat::Json => {
+                let document = DiffDocument {
+                    name,
+                    local_path: local.clone(),
+                    com_mojang_path: com_mojang,
+                    added: changes.added.len(),
+                    changed: changes.changed.len(),
+                    removed: changes.removed.len(),
+                    files: (!stat).then_some(files),
+                };
+                println!("{}", serde_json::to_string_pretty(&document).unwrap());
+            }
+            OutputFormat::Plain if stat => {
+                println!("added\t{}", changes.added.len());
+                println!("changed\t{}", changes.changed.len());
+                println!("removed\t{}", changes.removed.len());
+            }
+            OutputFormat::Plain => {
+                let size =
+                    |size: Option<u64>| size.map_or("-".to_string(), |size| size.to_string());
+                for file in files {
+                    println!(
+                        "{}\t{}\t{}\t{}",
+                        file.status.as_str(),
+                        file.path.display(),
+                        size(file.local_size),
+                        size(file.com_mojang_size)
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Shows whether each world is in sync between the local project and
+    /// `com.mojang`, and if not, on which side it was modified.
+    pub fn status(self, format: OutputFormat) -> Result<()> {
+        let names: Vec<_> = self
+            .local_worlds
+            .keys()
+            .chain(self.com_mojang_worlds.iter())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        let states = state::load_states()?;
+        let statuses = names
+            .par_iter()
+            .map(|name| self.world_status(name, states.get(*name)))
+            .collect::<Result<Vec<_>>>()?;
+
+        match format {
+            OutputFormat::Tree => {
+                let mut output = String::new();
+                write_tree(
+                    &mut output,
+                    names.iter().zip(&statuses).map(|(name, status)| {
+                        let status = match status {
+                            WorldStatus::Identical => format!(cstr!("<g>({})</>"), status),
+                            WorldStatus::Diverged => format!(cstr!("<r>({})</>"), status),
+                            _ => format!(cstr!("<y>({})</>"), status),
+                        };
+                        format!("{name} {status}")
+                    }),
+                );
+
+                if names.is_empty() {
+                    log::info!("there are no worlds");
+                } else {
+                    log::info!("checking the status of all worlds..\n{output}");
+                }
+            }
+            OutputFormat::Json => {
+                let document = StatusDocument {
+                    worlds: names
+                        .iter()
+                        .zip(&statuses)
+                        .map(|(name, status)| StatusEntry {
+                            name: name.to_string(),
+                            status: status.as_str(),
+                        })
+                        .collect(),
+                };
+                println!("{}", serde_json::to_string_pretty(&document).unwrap());
+            }
+            OutputFormat::Plain => {
+                for (name, status) in names.iter().zip(&statuses) {
+                    println!("{name}\t{}", status.as_str());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Works out the status of a world. If it was synced before, its state
+    /// tells exactly which side changed since, otherwise modification times
+    /// are used to guess.
+    fn world_status(&self, name: &str, state: Option<&WorldState>) -> Result<WorldStatus> {
+        let Some(local) = self.local_worlds.get(name) else {
+            return Ok(WorldStatus::OnlyComMojang);
+        };
+        if !self.com_mojang_worlds.contains(name) {
+            return Ok(WorldStatus::OnlyLocal);
+        }
+
+        let com_mojang = self.com_mojang.join(name);
+        let ignores = self.world_ignores(name)?;
+        let modified = match state {
+            Some(state) => state
+                .local_changed(local, &ignores)
+                .and_then(|local_changed| {
+                    Ok((
+                        local_changed,
+                        state.com_mojang_changed(&com_mojang, &ignores)?,
+                    ))
+                }),
+            None => sync::modified(local, &com_mojang, &ignores.either())
+                .map(|modified| (modified.from, modified.to)),
+        }
+        .map_err(|source| Error::WorldDiffFailure {
+            source,
+            name: name.to_string(),
+        })?;
+        Ok(match modified {
+            (false, false) => WorldStatus::Identical,
+            (false, true) => WorldStatus::ModifiedInGame,
+            (true, false) => WorldStatus::ModifiedLocally,
+            (true, true) => WorldStatus::Diverged,
+        })
+    }
+
+    /// Collects every known world sorted by name.
+    fn listed_worlds(&self) -> Vec<ListedWorld> {
+        let names: BTreeSet<_> = self
+            .local_worlds
+            .keys()
+            .chain(self.com_mojang_worlds.iter())
+            .collect();
+
+        names
+            .into_iter()
+            .map(|name| ListedWorld {
+                name: name.clone(),
+                local_path: self.local_worlds.get(name).cloned(),
+                in_com_mojang: self.com_mojang_worlds.contains(name),
+                com_mojang_path: self.com_mojang.join(name),
+            })
+            .collect()
+    }
+
+    fn list_tree(self) {
         let mut output = String::new();
 
         let has_local_worlds = !self.local_worlds.is_empty();
@@ -181,6 +1286,7 @@ impl WorldManager {
                     path.display()
                 )
                 .unwrap();
+                write_metadata(&mut output, path);
                 if !is_last || has_com_mojang_worlds {
                     writeln!(output).unwrap();
                 }
@@ -189,7 +1295,7 @@ impl WorldManager {
 
         if has_com_mojang_worlds {
             writeln!(output, cstr!("<y>`--</> <s>com.mojang</>")).unwrap();
-            for (index, path) in self.com_mojang_worlds.keys().enumerate() {
+            for (index, path) in self.com_mojang_worlds.iter().enumerate() {
                 let is_last = self.com_mojang_worlds.len() - 1 == index;
                 write!(
                     output,
@@ -198,6 +1304,7 @@ impl WorldManager {
                     path
                 )
                 .unwrap();
+                write_metadata(&mut output, &self.com_mojang.join(path));
                 if !is_last {
                     writeln!(output).unwrap();
                 }
@@ -205,18 +1312,309 @@ impl WorldManager {
         }
 
         log::info!("listing all worlds at..\n{output}");
+    }
+}
 
-        Ok(())
+/// Writes each of `entries` as a branch of a tree, on a line of its own.
+fn write_tree(output: &mut String, entries: impl IntoIterator<Item = impl fmt::Display>) {
+    let mut entries = entries.into_iter().peekable();
+    while let Some(entry) = entries.next() {
+        let is_last = entries.peek().is_none();
+        write!(
+            output,
+            cstr!("<y>{}--</> {}"),
+            if is_last { '`' } else { '|' },
+            entry
+        )
+        .unwrap();
+        if !is_last {
+            writeln!(output).unwrap();
+        }
+    }
+}
+
+/// Formats the files of a diff as a tree, with their sizes.
+fn diff_tree(files: &[DiffedFile]) -> String {
+    let mut output = String::new();
+    write_tree(
+        &mut output,
+        files.iter().map(|file| {
+            let path = file.path.display();
+            let size = |size: Option<u64>| format_size(size.unwrap_or_default());
+            match file.status {
+                DiffStatus::Added => format!(
+                    cstr!("<g>+ {}</> <dim>({})</>"),
+                    path,
+                    size(file.com_mojang_size)
+                ),
+                DiffStatus::Changed => format!(
+                    cstr!("<y>~ {}</> <dim>({} -> {})</>"),
+                    path,
+                    size(file.local_size),
+                    size(file.com_mojang_size)
+                ),
+                DiffStatus::Removed => format!(
+                    cstr!("<r>- {}</> <dim>({})</>"),
+                    path,
+                    size(file.local_size)
+                ),
+            }
+        }),
+    );
+
+    output
+}
+
+/// Appends the metadata of the world at `path` to a `list` entry, if it has
+/// any.
+fn write_metadata(output: &mut String, path: &Path) {
+    let metadata = WorldMetadata::read(path);
+    if !metadata.is_empty() {
+        write!(output, cstr!(" <dim>({})</>"), metadata).unwrap();
+    }
+}
+
+fn copy_world(from: &Path, to: &Path, ignore: &Ignore) -> Result<()> {
+    copy::copy_dir(from, to, ignore).map_err(|source| Error::WorldCopyFailure {
+        source,
+        from: from.to_path_buf(),
+        to: to.to_path_buf(),
+    })
+}
+
+/// Refuses to touch the `com.mojang` world at `path` while Minecraft seems to
+/// have it open, unless forced to.
+fn check_in_use(name: &str, path: &Path, options: CopyOptions) -> Result<()> {
+    let in_use = if options.settled {
+        in_use::check_open(path)
+    } else {
+        in_use::check(path)
+    };
+    let Some(reason) = in_use else {
+        return Ok(());
+    };
+    if !options.force {
+        return Err(Error::WorldInUse {
+            name: name.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+
+    log::warn!(
+        "`{}` seems to be open in Minecraft, since {reason}",
+        path.display()
+    );
+    Ok(())
+}
+
+/// Refuses to replace the world at `path` if it was `changed` since it was last
+/// synced, unless `force` is set.
+fn check_clobber(
+    changed: bool,
+    path: &Path,
+    force: bool,
+    error: impl FnOnce() -> Error,
+) -> Result<()> {
+    if !changed {
+        return Ok(());
+    }
+    if !force {
+        return Err(error());
     }
+
+    log::warn!(
+        "overwriting changes made to `{}` since it was last synced",
+        path.display()
+    );
+    Ok(())
 }
 
-fn copy_world(from: &Path, to: &Path) -> Result<()> {
-    let options = CopyOptions::new().content_only(true);
-    dir::copy(from, to, &options).map_err(|source| Error::WorldCopyFailure {
+/// Makes the world at `to` match the one at `from` by only copying the files
+/// that differ. This happens in place, so unlike a full copy, the world at `to`
+/// is not backed up. New worlds are still staged, so they never end up half
+/// copied.
+fn sync_world(from: &Path, to: &Path, compare: Compare, ignore: &Ignore) -> Result<Changes> {
+    let copy_failure = |source| Error::WorldCopyFailure {
         source,
         from: from.to_path_buf(),
         to: to.to_path_buf(),
-    })?;
+    };
+
+    if to.exists() {
+        return sync::sync(from, to, compare, ignore).map_err(copy_failure);
+    }
+
+    let staging = Staging::new(to)?;
+    let changes = sync::sync(from, staging.path(), compare, ignore).map_err(copy_failure)?;
+    staging.commit()?;
+    Ok(changes)
+}
+
+/// Cleans up after a previous run that was interrupted while replacing a world
+/// in `dir`. Returns the worlds that were put back in place, along with where
+/// they were found.
+fn clean_up_leftovers(
+    dir: &Path,
+    side: Side,
+    backups: &BackupStore,
+) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let mut recovered = Vec::new();
+    for leftover in staging::leftovers(dir)? {
+        match leftover {
+            Leftover::Staging(path) => {
+                log::info!("removing unfinished copy `{}`", path.display());
+                fs::remove_dir_all(&path).map_err(|source| Error::WorldAccessFailure {
+                    source,
+                    path: path.clone(),
+                })?;
+            }
+            // The new world never made it into place, so put the old one back.
+            Leftover::Old { path, target } if !target.exists() => {
+                log::info!("recovering `{}`", target.display());
+                staging::rename(&path, &target)?;
+                recovered.push((path, target));
+            }
+            // The new world was committed, but the old one was never backed up.
+            Leftover::Old { path, target } => {
+                discard_world(
+                    backups,
+                    &world_name_from_path(&target),
+                    side,
+                    &path,
+                    &target,
+                )?;
+            }
+        }
+    }
+
+    Ok(recovered)
+}
+
+/// Cleans up after a previous run that was interrupted while exporting a pack
+/// to `dir`. Packs that were replaced are deleted rather than backed up, since
+/// they are copies of the project's packs.
+fn clean_up_pack_leftovers(dir: &Path) -> Result<()> {
+    for leftover in staging::leftovers(dir)? {
+        let path = match leftover {
+            Leftover::Staging(path) => {
+                log::info!("removing unfinished copy `{}`", path.display());
+                path
+            }
+            Leftover::Old { path, target } if !target.exists() => {
+                log::info!("recovering `{}`", target.display());
+                staging::rename(&path, &target)?;
+                continue;
+            }
+            Leftover::Old { path, .. } => path,
+        };
+        fs::remove_dir_all(&path).map_err(|source| Error::WorldAccessFailure { source, path })?;
+    }
+
+    Ok(())
+}
+
+/// Backs up the `side` copy of the world at `path`, which was previously
+/// located at `origin`.
+fn discard_world(
+    backups: &BackupStore,
+    name: &str,
+    side: Side,
+    path: &Path,
+    origin: &Path,
+) -> Result<()> {
+    if let Some(snapshot) = backups.discard(name, side, path)? {
+        log::info!(
+            "backed up `{}` to `{}`",
+            origin.display(),
+            snapshot.display()
+        );
+    }
+
+    Ok(())
+}
+
+/// What happened to a world in a batch that was not replaced as a whole.
+enum BatchOutcome {
+    Failed,
+    Discarded,
+    RolledBack,
+    RollbackFailed(Error),
+}
+
+impl fmt::Display for BatchOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Failed => write!(f, "failed"),
+            Self::Discarded => write!(f, "not changed, discarded its copy"),
+            Self::RolledBack => write!(f, "rolled back"),
+            Self::RollbackFailed(error) => write!(f, "could not be rolled back: {error}"),
+        }
+    }
+}
+
+/// Reports what happened to each world in a batch after one of them failed.
+fn report_batch(worlds: &[(String, PathBuf, PathBuf)], outcomes: Vec<BatchOutcome>) {
+    let mut output = String::new();
+    write_tree(
+        &mut output,
+        worlds
+            .iter()
+            .zip(&outcomes)
+            .map(|((_, _, to), outcome)| format!(cstr!("{} <dim>({})</>"), to.display(), outcome)),
+    );
+
+    let all_rolled_back = !outcomes
+        .iter()
+        .any(|outcome| matches!(outcome, BatchOutcome::RollbackFailed(_)));
+    if all_rolled_back {
+        log::warn!("no worlds were changed..\n{output}");
+    } else {
+        log::warn!("some worlds could not be rolled back..\n{output}");
+    }
+}
+
+/// Turns the errors of a batch of `total` worlds into a single result.
+fn batch_result(total: usize, mut errors: Vec<Error>) -> Result<()> {
+    match errors.len() {
+        0 => Ok(()),
+        1 => Err(errors.pop().unwrap()),
+        _ => Err(Error::WorldsCopyFailure { total, errors }),
+    }
+}
+
+/// Formats a size in bytes using binary units, e.g. `1.5 MiB`.
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64;
+    let mut unit = "B";
+    for next_unit in UNITS {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = next_unit;
+    }
+    format!("{size:.1} {unit}")
+}
+
+/// Fails with [`Error::NoMatchingWorlds`] if any of `names` cannot be found.
+fn ensure_all_found(names: &BTreeSet<String>, exists: impl Fn(&str) -> bool) -> Result<()> {
+    let names_not_found: Vec<_> = names
+        .iter()
+        .filter(|&name| !exists(name))
+        .cloned()
+        .collect();
+
+    if !names_not_found.is_empty() {
+        return Err(Error::NoMatchingWorlds(NoMatchingWorldsError {
+            names: names_not_found,
+        }));
+    }
+
     Ok(())
 }
 
@@ -1,7 +1,15 @@
-use std::{env, fs, path::PathBuf};
+use std::{
+    env, fs,
+    io::{BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+    process::{self, Stdio},
+    thread,
+    time::{Duration, UNIX_EPOCH},
+};
 
 use fs_extra::dir::{self, CopyOptions};
 use insta_cmd::{assert_cmd_snapshot, get_cargo_bin, Command};
+use walkdir::WalkDir;
 
 const COM_MOJANG: &str = "com.mojang";
 const MINECRAFT_WORLDS: &str = "minecraftWorlds";
@@ -22,8 +30,47 @@ macro_rules! fn_name {
     }};
 }
 
+/// Replaces backup timestamps in snapshots, since they change on every run.
+fn filter_timestamps() -> impl Sized {
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"\d{8}T\d{6}\.\d{3}Z", "[TIMESTAMP]");
+    settings.bind_to_scope()
+}
+
+/// Sets the modification time of every file in `dir` to `secs` after the Unix
+/// epoch, since copying the test data doesn't keep them.
+fn set_modified(dir: &Path, secs: u64) {
+    let time = UNIX_EPOCH + Duration::from_secs(secs);
+    for entry in WalkDir::new(dir) {
+        let entry = entry.unwrap();
+        if entry.file_type().is_file() {
+            fs::File::options()
+                .write(true)
+                .open(entry.path())
+                .unwrap()
+                .set_modified(time)
+                .unwrap();
+        }
+    }
+}
+
+/// Takes a POSIX record lock on the whole of `file` until it is closed, like
+/// LevelDB does with `db/LOCK`.
+#[cfg(unix)]
+fn lock_record(file: &fs::File) {
+    use std::os::fd::AsRawFd;
+
+    // SAFETY: `flock` is plain data, and the file stays open for the call.
+    let mut lock: libc::flock = unsafe { std::mem::zeroed() };
+    lock.l_type = libc::F_WRLCK as _;
+    lock.l_whence = libc::SEEK_SET as _;
+    let result = unsafe { libc::fcntl(file.as_raw_fd(), libc::F_SETLK, &lock) };
+    assert_eq!(result, 0, "should lock the file");
+}
+
 struct HazeTest {
     temp_dir: PathBuf,
+    com_mojang: Option<&'static str>,
     command: Command,
 }
 
@@ -31,7 +78,7 @@ impl HazeTest {
     fn new<'a>(
         name: &'static str,
         args: impl IntoIterator<Item = &'a str>,
-        com_mojang: Option<&str>,
+        com_mojang: Option<&'static str>,
     ) -> Self {
         let temp_dir = env::temp_dir().join(name);
         fs::create_dir(&temp_dir).expect("should create temp dir");
@@ -45,14 +92,26 @@ impl HazeTest {
 
         std::fs::read_dir(&testdata).unwrap();
 
+        let mut test = Self {
+            temp_dir,
+            com_mojang,
+            command: Command::new(get_cargo_bin("haze")),
+        };
+        test.command = test.command(args);
+        test
+    }
+
+    /// Another invocation of Haze in the same test directory, for tests that
+    /// run more than one command.
+    fn command<'a>(&self, args: impl IntoIterator<Item = &'a str>) -> Command {
         let mut command = Command::new(get_cargo_bin("haze"));
-        command.args(args).current_dir(&temp_dir);
+        command.args(args).current_dir(&self.temp_dir);
 
-        if let Some(com_mojang) = com_mojang {
+        if let Some(com_mojang) = self.com_mojang {
             command.env("COM_MOJANG", com_mojang);
         }
 
-        Self { temp_dir, command }
+        command
     }
 }
 
@@ -222,6 +281,7 @@ fn export() {
 
 #[test]
 fn export_with_overwrite() {
+    let _settings = filter_timestamps();
     let mut test = HazeTest::new(
         fn_name!(),
         ["export", "--overwrite", "foo"],
@@ -235,6 +295,7 @@ fn export_with_overwrite() {
     ----- stdout -----
 
     ----- stderr -----
+    info: backed up `com.mojang/minecraftWorlds/foo` to `.haze/backups/foo/[TIMESTAMP]/com.mojang`
     info: exported `worlds/foo` to `com.mojang/minecraftWorlds/foo`
     "#);
 
@@ -245,6 +306,7 @@ fn export_with_overwrite() {
     ----- stdout -----
 
     ----- stderr -----
+    info: backed up `com.mojang\minecraftWorlds\foo` to `.haze\backups\foo\[TIMESTAMP]\com.mojang`
     info: exported `worlds\foo` to `com.mojang\minecraftWorlds\foo`
     "#);
 }
@@ -282,6 +344,7 @@ fn import_without_local_match() {
 #[test]
 fn import() {
     let world_to_import = "foo";
+    let _settings = filter_timestamps();
     let mut test = HazeTest::new(fn_name!(), ["import", world_to_import], Some(COM_MOJANG));
 
     #[cfg(unix)]
@@ -291,6 +354,7 @@ fn import() {
     ----- stdout -----
 
     ----- stderr -----
+    info: backed up `worlds/foo` to `.haze/backups/foo/[TIMESTAMP]/local`
     info: imported `com.mojang/minecraftWorlds/foo` to `worlds/foo`
     "#);
 
@@ -301,6 +365,7 @@ fn import() {
     ----- stdout -----
 
     ----- stderr -----
+    info: backed up `worlds\foo` to `.haze\backups\foo\[TIMESTAMP]\local`
     info: imported `com.mojang\minecraftWorlds\foo` to `worlds\foo`
     "#);
 
@@ -311,3 +376,1692 @@ fn import() {
         imported_world.display()
     );
 }
+
+#[test]
+fn pack() {
+    let mut test = HazeTest::new(fn_name!(), ["pack", "foo"], Some(COM_MOJANG));
+
+    #[cfg(unix)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: packed `worlds/foo` into `./foo.mcworld`
+    "#);
+
+    #[cfg(windows)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: packed `worlds\foo` into `.\foo.mcworld`
+    "#);
+
+    let archive = fs::File::open(test.temp_dir.join("foo.mcworld")).expect("should create archive");
+    let mut archive = zip::ZipArchive::new(archive).expect("should be a valid archive");
+    assert!(
+        archive.by_name("level.dat").is_ok(),
+        "expected `level.dat` to be at the archive root"
+    );
+}
+
+#[test]
+fn import_from_archive() {
+    let _settings = filter_timestamps();
+    let mut test = HazeTest::new(
+        fn_name!(),
+        ["import", "--from-archive", "foo.mcworld", "foo"],
+        Some(COM_MOJANG),
+    );
+
+    #[cfg(unix)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: backed up `worlds/foo` to `.haze/backups/foo/[TIMESTAMP]/local`
+    info: imported `foo.mcworld` to `worlds/foo`
+    "#);
+
+    #[cfg(windows)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: backed up `worlds\foo` to `.haze\backups\foo\[TIMESTAMP]\local`
+    info: imported `foo.mcworld` to `worlds\foo`
+    "#);
+
+    let imported_world = test.temp_dir.join("worlds").join("foo");
+    assert!(
+        imported_world.join("levelname.txt").exists(),
+        "expected the archive contents to have been imported"
+    );
+    assert!(
+        !imported_world.join("stale.txt").exists(),
+        "expected the old local world contents to have been replaced"
+    );
+}
+
+#[test]
+fn archive_without_level_dat() {
+    let mut test = HazeTest::new(
+        fn_name!(),
+        ["import", "--from-archive", "foo.zip", "foo"],
+        Some(COM_MOJANG),
+    );
+
+    assert_cmd_snapshot!(test.command, @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: the archive `foo.zip` does not contain a world
+      help: a world archive must contain a `level.dat` file
+    "#);
+
+    assert!(
+        test.temp_dir
+            .join("worlds")
+            .join("foo")
+            .join("level.dat")
+            .exists(),
+        "expected the local world to be left untouched"
+    );
+}
+
+#[test]
+fn template() {
+    let mut test = HazeTest::new(
+        fn_name!(),
+        ["template", "--template-version", "1.2.0", "foo"],
+        Some(COM_MOJANG),
+    );
+
+    #[cfg(unix)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: packed `worlds/foo` into `./foo.mctemplate` (version 1.2.0)
+    "#);
+
+    #[cfg(windows)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: packed `worlds\foo` into `.\foo.mctemplate` (version 1.2.0)
+    "#);
+
+    let read_manifest = || -> serde_json::Value {
+        let archive =
+            fs::File::open(test.temp_dir.join("foo.mctemplate")).expect("should create template");
+        let mut archive = zip::ZipArchive::new(archive).expect("should be a valid archive");
+        let manifest = archive
+            .by_name("manifest.json")
+            .expect("expected `manifest.json` to be at the archive root");
+        serde_json::from_reader(manifest).expect("should be a valid manifest")
+    };
+
+    let manifest = read_manifest();
+    assert_eq!(manifest["header"]["name"], "Foo World");
+    assert_eq!(manifest["header"]["version"], serde_json::json!([1, 2, 0]));
+    assert_eq!(manifest["modules"][0]["type"], "world_template");
+
+    // Packaging the world again must reuse the UUIDs and version stored in
+    // `.haze/templates.json`.
+    let mut command = test.command(["template", "foo"]);
+    assert!(command.output().unwrap().status.success());
+
+    let repacked_manifest = read_manifest();
+    assert_eq!(
+        manifest["header"]["uuid"],
+        repacked_manifest["header"]["uuid"]
+    );
+    assert_eq!(
+        manifest["modules"][0]["uuid"],
+        repacked_manifest["modules"][0]["uuid"]
+    );
+    assert_eq!(
+        repacked_manifest["header"]["version"],
+        serde_json::json!([1, 2, 0])
+    );
+}
+
+#[test]
+fn list() {
+    let mut test = HazeTest::new(fn_name!(), ["list"], Some(COM_MOJANG));
+
+    #[cfg(unix)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: listing all worlds at..
+    |-- local project
+    |   `-- worlds/foo ("Foo World", creative, v1.21.40.3, last played 2024-10-15T13:46:40Z)
+    `-- com.mojang
+        `-- Ab3dEfG= ("Bar World")
+    "#);
+
+    #[cfg(windows)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: listing all worlds at..
+    |-- local project
+    |   `-- worlds\foo ("Foo World", creative, v1.21.40.3, last played 2024-10-15T13:46:40Z)
+    `-- com.mojang
+        `-- Ab3dEfG= ("Bar World")
+    "#);
+}
+
+#[test]
+fn list_as_json() {
+    let mut test = HazeTest::new(fn_name!(), ["list", "--format", "json"], Some(COM_MOJANG));
+
+    #[cfg(unix)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    {
+      "worlds": [
+        {
+          "name": "Ab3dEfG=",
+          "localPath": null,
+          "inComMojang": true,
+          "comMojangPath": "com.mojang/minecraftWorlds/Ab3dEfG="
+        },
+        {
+          "name": "bar",
+          "localPath": "worlds/bar",
+          "inComMojang": false,
+          "comMojangPath": "com.mojang/minecraftWorlds/bar"
+        },
+        {
+          "name": "foo",
+          "localPath": "worlds/foo",
+          "inComMojang": true,
+          "comMojangPath": "com.mojang/minecraftWorlds/foo"
+        }
+      ]
+    }
+
+    ----- stderr -----
+    "#);
+
+    #[cfg(windows)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    {
+      "worlds": [
+        {
+          "name": "Ab3dEfG=",
+          "localPath": null,
+          "inComMojang": true,
+          "comMojangPath": "com.mojang\\minecraftWorlds\\Ab3dEfG="
+        },
+        {
+          "name": "bar",
+          "localPath": "worlds\\bar",
+          "inComMojang": false,
+          "comMojangPath": "com.mojang\\minecraftWorlds\\bar"
+        },
+        {
+          "name": "foo",
+          "localPath": "worlds\\foo",
+          "inComMojang": true,
+          "comMojangPath": "com.mojang\\minecraftWorlds\\foo"
+        }
+      ]
+    }
+
+    ----- stderr -----
+    "#);
+}
+
+#[test]
+fn backup_retention() {
+    let mut test = HazeTest::new(fn_name!(), ["import", "foo"], Some(COM_MOJANG));
+    assert!(test.command.output().unwrap().status.success());
+
+    let backups_dir = test.temp_dir.join(".haze").join("backups").join("foo");
+    let mut backups: Vec<_> = fs::read_dir(&backups_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    backups.sort();
+
+    assert_eq!(backups.len(), 2, "expected the oldest backup to be pruned");
+    assert!(backups[0].ends_with("20000102T000000.000Z"));
+    assert!(
+        backups[1].join("local").join("local.txt").exists(),
+        "expected the replaced local world to have been backed up"
+    );
+}
+
+#[test]
+fn restore() {
+    let _settings = filter_timestamps();
+    let mut test = HazeTest::new(
+        fn_name!(),
+        ["restore", "foo", "--at", "2000-01-01T00:00:00.000Z"],
+        Some(COM_MOJANG),
+    );
+
+    #[cfg(unix)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: backed up `worlds/foo` to `.haze/backups/foo/[TIMESTAMP]/local`
+    info: restored `worlds/foo` from the backup at 2000-01-01T00:00:00.000Z
+    "#);
+
+    #[cfg(windows)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: backed up `worlds\foo` to `.haze\backups\foo\[TIMESTAMP]\local`
+    info: restored `worlds\foo` from the backup at 2000-01-01T00:00:00.000Z
+    "#);
+
+    let restored_world = test.temp_dir.join("worlds").join("foo");
+    assert_eq!(
+        fs::read_to_string(restored_world.join("marker.txt"))
+            .unwrap()
+            .trim(),
+        "old",
+        "expected the older backup to have been restored"
+    );
+}
+
+#[test]
+fn restore_from_same_side() {
+    let _settings = filter_timestamps();
+    let mut test = HazeTest::new(
+        fn_name!(),
+        ["export", "foo", "--overwrite"],
+        Some(COM_MOJANG),
+    );
+    set_modified(&test.temp_dir, 1_000);
+    assert!(test.command.output().unwrap().status.success());
+
+    let local_world = test.temp_dir.join("worlds").join("foo");
+    let com_mojang_world = test
+        .temp_dir
+        .join(COM_MOJANG)
+        .join(MINECRAFT_WORLDS)
+        .join("foo");
+    fs::write(com_mojang_world.join("marker.txt"), "played").unwrap();
+    assert!(test
+        .command(["import", "foo"])
+        .output()
+        .unwrap()
+        .status
+        .success());
+
+    // The latest backup is of the local world, which must not end up in
+    // `com.mojang`.
+    assert!(test
+        .command(["restore", "foo", "--target", "com.mojang"])
+        .output()
+        .unwrap()
+        .status
+        .success());
+    assert_eq!(
+        fs::read_to_string(com_mojang_world.join("marker.txt"))
+            .unwrap()
+            .trim(),
+        "game",
+        "expected the backup of the `com.mojang` world to have been restored"
+    );
+
+    assert!(test
+        .command(["restore", "foo"])
+        .output()
+        .unwrap()
+        .status
+        .success());
+    assert_eq!(
+        fs::read_to_string(local_world.join("marker.txt"))
+            .unwrap()
+            .trim(),
+        "local",
+        "expected the backup of the local world to have been restored"
+    );
+
+    let backups_dir = test.temp_dir.join(".haze").join("backups").join("foo");
+    let com_mojang_backup = fs::read_dir(&backups_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.join(COM_MOJANG).exists())
+        .min()
+        .unwrap();
+    let timestamp = com_mojang_backup.file_name().unwrap().to_str().unwrap();
+    assert_cmd_snapshot!(test.command(["restore", "foo", "--at", timestamp]), @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: the backup of `foo` from `[TIMESTAMP]` is of the com.mojang copy,
+      | not the local one
+      help: use --target com.mojang to restore it there instead
+    "#);
+}
+
+#[test]
+fn backups() {
+    let mut test = HazeTest::new(fn_name!(), ["backups", "foo"], Some(COM_MOJANG));
+
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: listing backups of `foo`..
+    |-- 2000-01-01T00:00:00.000Z (com.mojang, 4 B)
+    `-- 2000-01-02T00:00:00.000Z (local, 6 B)
+    "#);
+}
+
+#[test]
+fn staging_leftovers() {
+    let _settings = filter_timestamps();
+    let mut test = HazeTest::new(fn_name!(), ["list", "--format", "plain"], Some(COM_MOJANG));
+
+    #[cfg(unix)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    bar	worlds/bar	-
+    foo	worlds/foo	-
+
+    ----- stderr -----
+    info: removing unfinished copy `com.mojang/minecraftWorlds/.foo.haze-staging`
+    info: recovering `worlds/bar`
+    info: backed up `worlds/foo` to `.haze/backups/foo/[TIMESTAMP]/local`
+    "#);
+
+    #[cfg(windows)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    bar	worlds\bar	-
+    foo	worlds\foo	-
+
+    ----- stderr -----
+    info: removing unfinished copy `com.mojang\minecraftWorlds\.foo.haze-staging`
+    info: recovering `worlds\bar`
+    info: backed up `worlds\foo` to `.haze\backups\foo\[TIMESTAMP]\local`
+    "#);
+
+    let com_mojang = test.temp_dir.join(COM_MOJANG).join(MINECRAFT_WORLDS);
+    assert!(
+        !com_mojang.join(".foo.haze-staging").exists(),
+        "expected the unfinished copy to have been removed"
+    );
+    assert!(
+        test.temp_dir.join("worlds").join("bar").exists(),
+        "expected the world that was moved aside to have been recovered"
+    );
+    assert!(
+        !test.temp_dir.join("worlds").join(".foo.haze-old").exists(),
+        "expected the replaced world to have been backed up"
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn atomic_export() {
+    let mut test = HazeTest::new(
+        fn_name!(),
+        ["export", "bar", "foo", "--overwrite", "--atomic"],
+        Some(COM_MOJANG),
+    );
+    // A dangling symlink can't be copied, so exporting `foo` fails after `bar`
+    // was already copied.
+    std::os::unix::fs::symlink(
+        "missing",
+        test.temp_dir.join("worlds").join("foo").join("broken"),
+    )
+    .unwrap();
+
+    assert_cmd_snapshot!(test.command, @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    warning: no worlds were changed..
+    |-- com.mojang/minecraftWorlds/bar (not changed, discarded its copy)
+    `-- com.mojang/minecraftWorlds/foo (failed)
+    error: failed to copy world `worlds/foo` to `com.mojang/
+      | minecraftWorlds/.foo.haze-staging`
+      `-> No such file or directory (os error 2)
+    "#);
+
+    let com_mojang = test.temp_dir.join(COM_MOJANG).join(MINECRAFT_WORLDS);
+    assert!(
+        !com_mojang.join("bar").exists(),
+        "expected `bar` not to have been exported"
+    );
+    assert_eq!(
+        fs::read_to_string(com_mojang.join("foo").join("level.dat")).unwrap(),
+        "old\n",
+        "expected `foo` to have been left as is"
+    );
+    assert_eq!(fs::read_dir(&com_mojang).unwrap().count(), 1);
+}
+
+#[test]
+#[cfg(unix)]
+fn parallel_export() {
+    let mut test = HazeTest::new(
+        fn_name!(),
+        ["export", "-j", "2", "bar", "baz", "foo"],
+        Some(COM_MOJANG),
+    );
+    // Failing to export `baz` and `foo` shouldn't stop `bar` from being
+    // exported.
+    for name in ["baz", "foo"] {
+        std::os::unix::fs::symlink(
+            "missing",
+            test.temp_dir.join("worlds").join(name).join("broken"),
+        )
+        .unwrap();
+    }
+
+    assert_cmd_snapshot!(test.command, @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    info: exported `worlds/bar` to `com.mojang/minecraftWorlds/bar`
+    error: failed to copy 2 of 3 worlds
+    error: failed to copy world `worlds/baz` to `com.mojang/
+      | minecraftWorlds/.baz.haze-staging`
+      `-> No such file or directory (os error 2)
+    error: failed to copy world `worlds/foo` to `com.mojang/
+      | minecraftWorlds/.foo.haze-staging`
+      `-> No such file or directory (os error 2)
+    "#);
+
+    let com_mojang = test.temp_dir.join(COM_MOJANG).join(MINECRAFT_WORLDS);
+    assert!(com_mojang.join("bar").join("level.dat").exists());
+    assert!(!com_mojang.join("baz").exists());
+    assert!(!com_mojang.join("foo").exists());
+}
+
+#[test]
+fn incremental_export() {
+    let mut test = HazeTest::new(
+        fn_name!(),
+        [
+            "export",
+            "foo",
+            "--overwrite",
+            "--incremental",
+            "--checksum",
+        ],
+        Some(COM_MOJANG),
+    );
+    // Freshly copied worlds look like they were just saved by Minecraft.
+    set_modified(&test.temp_dir, 1_000);
+
+    #[cfg(unix)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: exported `worlds/foo` to `com.mojang/minecraftWorlds/foo` (1 added, 1 changed, 2 removed)
+    "#);
+
+    #[cfg(windows)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: exported `worlds\foo` to `com.mojang\minecraftWorlds\foo` (1 added, 1 changed, 2 removed)
+    "#);
+
+    let world = test
+        .temp_dir
+        .join(COM_MOJANG)
+        .join(MINECRAFT_WORLDS)
+        .join("foo");
+    assert_eq!(
+        fs::read_to_string(world.join("level.dat")).unwrap(),
+        "new\n",
+        "expected the changed file to have been copied"
+    );
+    assert!(world.join("added.txt").exists());
+    assert!(!world.join("removed.txt").exists());
+    assert!(!world.join("stale").exists());
+}
+
+#[test]
+fn export_with_ignores() {
+    let _settings = filter_timestamps();
+    let mut test = HazeTest::new(
+        fn_name!(),
+        ["export", "--overwrite", "foo"],
+        Some(COM_MOJANG),
+    );
+    set_modified(&test.temp_dir, 1_000);
+
+    #[cfg(unix)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: backed up `com.mojang/minecraftWorlds/foo` to `.haze/backups/foo/[TIMESTAMP]/com.mojang`
+    info: exported `worlds/foo` to `com.mojang/minecraftWorlds/foo`
+    "#);
+
+    #[cfg(windows)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: backed up `com.mojang\minecraftWorlds\foo` to `.haze\backups\foo\[TIMESTAMP]\com.mojang`
+    info: exported `worlds\foo` to `com.mojang\minecraftWorlds\foo`
+    "#);
+
+    let world = test
+        .temp_dir
+        .join(COM_MOJANG)
+        .join(MINECRAFT_WORLDS)
+        .join("foo");
+    assert_eq!(
+        fs::read_to_string(world.join("level.dat")).unwrap(),
+        "new\n"
+    );
+    assert!(world.join("db").join("000001.ldb").exists());
+    assert!(!world.join("db").join("LOG").exists());
+    assert!(!world.join("scratch.tmp").exists());
+    assert!(!world.join(".hazeignore").exists());
+    assert!(!world.join("notes").join("todo.md").exists());
+    assert!(
+        world.join("notes").join("old.md").exists(),
+        "expected ignored files in `com.mojang` to have been kept"
+    );
+}
+
+#[test]
+fn import_with_ignores() {
+    let mut test = HazeTest::new(
+        fn_name!(),
+        ["import", "foo", "--incremental"],
+        Some(COM_MOJANG),
+    );
+    set_modified(&test.temp_dir, 1_000);
+
+    #[cfg(unix)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: imported `com.mojang/minecraftWorlds/foo` to `worlds/foo` (1 added, 1 changed, 0 removed)
+    "#);
+
+    #[cfg(windows)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: imported `com.mojang\minecraftWorlds\foo` to `worlds\foo` (1 added, 1 changed, 0 removed)
+    "#);
+
+    let world = test.temp_dir.join("worlds").join("foo");
+    assert_eq!(
+        fs::read_to_string(world.join("level.dat")).unwrap(),
+        "played\n"
+    );
+    assert!(world.join("db").join("000001.ldb").exists());
+    assert!(!world.join("db").join("LOG.old").exists());
+    assert_eq!(
+        fs::read_to_string(world.join("db").join("LOG")).unwrap(),
+        "local\n"
+    );
+    assert!(world.join(".hazeignore").exists());
+}
+
+#[test]
+fn import_with_preserve() {
+    let _settings = filter_timestamps();
+    let mut test = HazeTest::new(
+        fn_name!(),
+        ["import", "foo", "--preserve"],
+        Some(COM_MOJANG),
+    );
+    set_modified(&test.temp_dir, 1_000);
+
+    #[cfg(unix)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: backed up `worlds/foo` to `.haze/backups/foo/[TIMESTAMP]/local`
+    info: imported `com.mojang/minecraftWorlds/foo` to `worlds/foo`
+    "#);
+
+    #[cfg(windows)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: backed up `worlds\foo` to `.haze\backups\foo\[TIMESTAMP]\local`
+    info: imported `com.mojang\minecraftWorlds\foo` to `worlds\foo`
+    "#);
+
+    let world = test.temp_dir.join("worlds").join("foo");
+    assert_eq!(
+        fs::read_to_string(world.join("level.dat")).unwrap(),
+        "played\n"
+    );
+    assert!(world.join("db").join("000003.ldb").exists());
+    assert!(!world.join("db").join("000001.ldb").exists());
+    assert!(!world.join("db").join("000002.ldb").exists());
+    assert_eq!(
+        fs::read_to_string(world.join("README.md")).unwrap(),
+        "# foo\n"
+    );
+    assert!(world.join("scripts").join("test.sh").exists());
+}
+
+#[test]
+fn export_with_linked_packs() {
+    let mut test = HazeTest::new(
+        fn_name!(),
+        ["export", "foo", "--link-packs"],
+        Some(COM_MOJANG),
+    );
+
+    #[cfg(unix)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: exported `worlds/foo` to `com.mojang/minecraftWorlds/foo`
+    "#);
+
+    #[cfg(windows)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: exported `worlds\foo` to `com.mojang\minecraftWorlds\foo`
+    "#);
+
+    let world = test
+        .temp_dir
+        .join(COM_MOJANG)
+        .join(MINECRAFT_WORLDS)
+        .join("foo");
+    let read_json = |name: &str| -> serde_json::Value {
+        serde_json::from_str(&fs::read_to_string(world.join(name)).unwrap()).unwrap()
+    };
+    assert_eq!(
+        read_json("world_behavior_packs.json"),
+        serde_json::json!([
+            {
+                "pack_id": "0f4b4a7e-5b4e-4a43-9d3c-3f6b2b3e7a11",
+                "version": [2, 0, 0],
+                "subpack": "low"
+            },
+            {
+                "pack_id": "6b0bdb89-1e1d-4f8a-8b2a-0d8f0e6d1c01",
+                "version": [1, 2, 0]
+            }
+        ])
+    );
+    assert_eq!(
+        read_json("world_resource_packs.json"),
+        serde_json::json!([
+            {
+                "pack_id": "6b0bdb89-1e1d-4f8a-8b2a-0d8f0e6d1c03",
+                "version": [0, 3, 1]
+            }
+        ])
+    );
+    assert!(
+        !test
+            .temp_dir
+            .join("worlds")
+            .join("foo")
+            .join("world_resource_packs.json")
+            .exists(),
+        "expected the local world to be untouched"
+    );
+}
+
+#[test]
+fn check() {
+    let mut test = HazeTest::new(fn_name!(), ["check"], Some(COM_MOJANG));
+
+    #[cfg(unix)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    warning: `foo` embeds `worlds/foo/behavior_packs/junk`, which has no valid `manifest.json`
+    warning: `foo` uses version 1.0.0 of behavior pack `6b0bdb89-1e1d-4f8a-8b2a-0d8f0e6d1c01`, but the project's pack is version 1.2.0
+    warning: `foo` embeds `worlds/foo/behavior_packs/old_bp`, which it doesn't use
+    warning: `foo` uses version 2.0.0 of resource pack `0e6a3c7f-52b1-4d0e-9c6f-7a4b2d8e9f10`, which is neither one of the project's packs nor embedded in the world
+    error: found 4 problems with the packs of worlds
+      help: edit the `world_behavior_packs.json` and `world_resource_packs.json`
+            of the local worlds, or the packs embedded in them, to fix them
+    "#);
+
+    #[cfg(windows)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    warning: `foo` embeds `worlds\foo\behavior_packs\junk`, which has no valid `manifest.json`
+    warning: `foo` uses version 1.0.0 of behavior pack `6b0bdb89-1e1d-4f8a-8b2a-0d8f0e6d1c01`, but the project's pack is version 1.2.0
+    warning: `foo` embeds `worlds\foo\behavior_packs\old_bp`, which it doesn't use
+    warning: `foo` uses version 2.0.0 of resource pack `0e6a3c7f-52b1-4d0e-9c6f-7a4b2d8e9f10`, which is neither one of the project's packs nor embedded in the world
+    error: found 4 problems with the packs of worlds
+      help: edit the `world_behavior_packs.json` and `world_resource_packs.json`
+            of the local worlds, or the packs embedded in them, to fix them
+    "#);
+}
+
+#[test]
+fn check_world() {
+    let mut test = HazeTest::new(fn_name!(), ["check", "bar"], Some(COM_MOJANG));
+
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: checked the packs of 1 world, found no problems
+    "#);
+
+    // `foo` does have a problem, so it must have been left out above.
+    let mut command = test.command(["check", "foo"]);
+    assert_cmd_snapshot!(command, @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    warning: `foo` uses version 1.0.0 of behavior pack `6b0bdb89-1e1d-4f8a-8b2a-0d8f0e6d1c01`, but the project's pack is version 1.2.0
+    error: found 1 problem with the packs of worlds
+      help: edit the `world_behavior_packs.json` and `world_resource_packs.json`
+            of the local worlds, or the packs embedded in them, to fix them
+    "#);
+}
+
+#[test]
+fn export_packs() {
+    let mut test = HazeTest::new(fn_name!(), ["export-packs"], Some(COM_MOJANG));
+
+    #[cfg(unix)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: removing unfinished copy `com.mojang/development_behavior_packs/.foo_bp.haze-staging`
+    info: exported `./packs/BP` to `com.mojang/development_behavior_packs/foo_bp`
+    info: exported `./packs/RP` to `com.mojang/development_resource_packs/foo_rp`
+    "#);
+
+    #[cfg(windows)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: removing unfinished copy `com.mojang\development_behavior_packs\.foo_bp.haze-staging`
+    info: exported `./packs/BP` to `com.mojang\development_behavior_packs\foo_bp`
+    info: exported `./packs/RP` to `com.mojang\development_resource_packs\foo_rp`
+    "#);
+
+    let com_mojang = test.temp_dir.join(COM_MOJANG);
+    let behavior_pack = com_mojang.join("development_behavior_packs").join("foo_bp");
+    assert!(behavior_pack.join("scripts").join("main.js").exists());
+    assert!(
+        !behavior_pack.join("stale.txt").exists(),
+        "expected the previously exported pack to have been replaced"
+    );
+    assert!(
+        !com_mojang
+            .join("development_behavior_packs")
+            .join(".foo_bp.haze-staging")
+            .exists(),
+        "expected the unfinished copy to have been removed"
+    );
+    assert!(com_mojang
+        .join("development_resource_packs")
+        .join("foo_rp")
+        .join("manifest.json")
+        .exists());
+}
+
+#[test]
+#[cfg(unix)]
+fn export_packs_with_symlink() {
+    let mut test = HazeTest::new(fn_name!(), ["export-packs", "--symlink"], Some(COM_MOJANG));
+
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: linked `./packs/BP` to `com.mojang/development_behavior_packs/foo_bp`
+    info: linked `./packs/RP` to `com.mojang/development_resource_packs/foo_rp`
+    "#);
+
+    let link = test
+        .temp_dir
+        .join(COM_MOJANG)
+        .join("development_behavior_packs")
+        .join("foo_bp");
+    assert!(fs::symlink_metadata(&link).unwrap().is_symlink());
+    assert_eq!(
+        fs::read_link(&link).unwrap(),
+        test.temp_dir
+            .join("packs")
+            .join("BP")
+            .canonicalize()
+            .unwrap()
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn export_packs_with_symlink_over_copy() {
+    let mut test = HazeTest::new(fn_name!(), ["export-packs", "--symlink"], Some(COM_MOJANG));
+
+    assert_cmd_snapshot!(test.command, @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: attempting to replace the copy of a pack at `com.mojang/
+      | development_behavior_packs/foo_bp` with a link
+      help: the copy may have changes of its own, use --force to replace it
+            anyway
+    "#);
+
+    let copy = test
+        .temp_dir
+        .join(COM_MOJANG)
+        .join("development_behavior_packs")
+        .join("foo_bp");
+    assert!(
+        copy.join("notes.txt").exists(),
+        "expected the copy of the pack to have been kept"
+    );
+
+    test.command.arg("--force");
+    assert!(test.command.output().unwrap().status.success());
+    assert!(fs::symlink_metadata(&copy).unwrap().is_symlink());
+}
+
+#[test]
+fn import_with_pack_references() {
+    let _settings = filter_timestamps();
+    let mut test = HazeTest::new(fn_name!(), ["import", "foo", "bar"], Some(COM_MOJANG));
+    set_modified(&test.temp_dir, 1_000);
+
+    #[cfg(unix)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: backed up `worlds/bar` to `.haze/backups/bar/[TIMESTAMP]/local`
+    info: imported `com.mojang/minecraftWorlds/bar` to `worlds/bar`
+    info: backed up `worlds/foo` to `.haze/backups/foo/[TIMESTAMP]/local`
+    info: imported `com.mojang/minecraftWorlds/foo` to `worlds/foo`
+    "#);
+
+    #[cfg(windows)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: backed up `worlds\bar` to `.haze\backups\bar\[TIMESTAMP]\local`
+    info: imported `com.mojang\minecraftWorlds\bar` to `worlds\bar`
+    info: backed up `worlds\foo` to `.haze\backups\foo\[TIMESTAMP]\local`
+    info: imported `com.mojang\minecraftWorlds\foo` to `worlds\foo`
+    "#);
+
+    let worlds = test.temp_dir.join("worlds");
+    let read_json = |world: &str, name: &str| -> serde_json::Value {
+        serde_json::from_str(&fs::read_to_string(worlds.join(world).join(name)).unwrap()).unwrap()
+    };
+    let other_pack = serde_json::json!({
+        "can_be_redownloaded": true,
+        "name": "Other BP",
+        "uuid": "0f4b4a7e-5b4e-4a43-9d3c-3f6b2b3e7a11",
+        "version": [2, 0, 0]
+    });
+
+    assert!(!worlds
+        .join("foo")
+        .join("world_behavior_packs.json")
+        .exists());
+    assert_eq!(
+        read_json("foo", "world_behavior_pack_history.json"),
+        serde_json::json!({ "packs": [other_pack] })
+    );
+
+    assert_eq!(
+        read_json("bar", "world_behavior_packs.json"),
+        serde_json::json!([
+            {
+                "pack_id": "6b0bdb89-1e1d-4f8a-8b2a-0d8f0e6d1c01",
+                "version": [1, 2, 0]
+            }
+        ])
+    );
+    assert_eq!(
+        read_json("bar", "world_behavior_pack_history.json"),
+        serde_json::json!({
+            "packs": [
+                {
+                    "can_be_redownloaded": false,
+                    "name": "Foo BP",
+                    "uuid": "6b0bdb89-1e1d-4f8a-8b2a-0d8f0e6d1c01",
+                    "version": [1, 2, 0]
+                },
+                other_pack
+            ]
+        })
+    );
+}
+
+#[test]
+fn export_with_embedded_packs() {
+    let mut test = HazeTest::new(
+        fn_name!(),
+        ["export", "foo", "--embed-packs"],
+        Some(COM_MOJANG),
+    );
+
+    #[cfg(unix)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: exported `worlds/foo` to `com.mojang/minecraftWorlds/foo`
+    "#);
+
+    #[cfg(windows)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: exported `worlds\foo` to `com.mojang\minecraftWorlds\foo`
+    "#);
+
+    let world = test
+        .temp_dir
+        .join(COM_MOJANG)
+        .join(MINECRAFT_WORLDS)
+        .join("foo");
+    assert!(world
+        .join("behavior_packs")
+        .join("foo_bp")
+        .join("scripts")
+        .join("main.js")
+        .exists());
+    assert!(world
+        .join("resource_packs")
+        .join("foo_rp")
+        .join("manifest.json")
+        .exists());
+    let behavior_packs: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(world.join("world_behavior_packs.json")).unwrap())
+            .unwrap();
+    assert_eq!(
+        behavior_packs,
+        serde_json::json!([
+            {
+                "pack_id": "6b0bdb89-1e1d-4f8a-8b2a-0d8f0e6d1c01",
+                "version": [1, 2, 0]
+            }
+        ])
+    );
+    assert!(
+        !test
+            .temp_dir
+            .join("worlds")
+            .join("foo")
+            .join("behavior_packs")
+            .exists(),
+        "expected the local world to be untouched"
+    );
+}
+
+#[test]
+fn import_after_embedding_packs() {
+    let mut test = HazeTest::new(
+        fn_name!(),
+        ["export", "foo", "--embed-packs"],
+        Some(COM_MOJANG),
+    );
+    assert!(test.command.output().unwrap().status.success());
+
+    // A pack that isn't the project's is imported like any other file.
+    let world = test
+        .temp_dir
+        .join(COM_MOJANG)
+        .join(MINECRAFT_WORLDS)
+        .join("foo");
+    let other_pack = world.join("behavior_packs").join("other_bp");
+    fs::create_dir_all(&other_pack).unwrap();
+    fs::write(
+        other_pack.join("manifest.json"),
+        r#"{"header": {"uuid": "0e6a3c7f-5d2b-4c1e-9f8a-7b6c5d4e3f21", "version": [1, 0, 0]}}"#,
+    )
+    .unwrap();
+    assert!(test
+        .command(["import", "foo"])
+        .output()
+        .unwrap()
+        .status
+        .success());
+
+    let local_world = test.temp_dir.join("worlds").join("foo");
+    assert!(local_world
+        .join("behavior_packs")
+        .join("other_bp")
+        .join("manifest.json")
+        .exists());
+    assert!(
+        !local_world.join("behavior_packs").join("foo_bp").exists()
+            && !local_world.join("resource_packs").exists(),
+        "expected the embedded project packs to stay in `com.mojang`"
+    );
+
+    assert_cmd_snapshot!(test.command(["status"]), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: checking the status of all worlds..
+    `-- foo (identical)
+    "#);
+}
+
+#[test]
+fn export_with_broken_packs() {
+    let _settings = filter_timestamps();
+    let mut test = HazeTest::new(
+        fn_name!(),
+        ["export", "foo", "--overwrite"],
+        Some(COM_MOJANG),
+    );
+
+    // The resource pack doesn't exist, which only matters to the commands
+    // that use the packs.
+    #[cfg(unix)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: backed up `com.mojang/minecraftWorlds/foo` to `.haze/backups/foo/[TIMESTAMP]/com.mojang`
+    info: exported `worlds/foo` to `com.mojang/minecraftWorlds/foo`
+    "#);
+
+    #[cfg(windows)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: backed up `com.mojang\minecraftWorlds\foo` to `.haze\backups\foo\[TIMESTAMP]\com.mojang`
+    info: exported `worlds\foo` to `com.mojang\minecraftWorlds\foo`
+    "#);
+
+    assert_cmd_snapshot!(test.command(["status"]), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: checking the status of all worlds..
+    `-- foo (identical)
+    "#);
+    assert!(test
+        .command(["import", "foo"])
+        .output()
+        .unwrap()
+        .status
+        .success());
+}
+
+#[test]
+fn pack_with_embedded_packs() {
+    let mut test = HazeTest::new(
+        fn_name!(),
+        ["pack", "foo", "--embed-packs"],
+        Some(COM_MOJANG),
+    );
+
+    #[cfg(unix)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: packed `worlds/foo` into `./foo.mcworld`
+    "#);
+
+    #[cfg(windows)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: packed `worlds\foo` into `.\foo.mcworld`
+    "#);
+
+    let archive = fs::File::open(test.temp_dir.join("foo.mcworld")).expect("should create archive");
+    let mut archive = zip::ZipArchive::new(archive).expect("should be a valid archive");
+    for name in [
+        "level.dat",
+        "world_behavior_packs.json",
+        "world_resource_packs.json",
+        "behavior_packs/foo_bp/scripts/main.js",
+        "resource_packs/foo_rp/manifest.json",
+    ] {
+        assert!(
+            archive.by_name(name).is_ok(),
+            "expected `{name}` in the archive"
+        );
+    }
+    assert!(
+        !test.temp_dir.join(".foo.haze-staging").exists(),
+        "expected the embedding copy to have been removed"
+    );
+}
+
+#[test]
+fn diff() {
+    let mut test = HazeTest::new(fn_name!(), ["diff", "foo", "--checksum"], Some(COM_MOJANG));
+
+    #[cfg(unix)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: comparing `worlds/foo` with `com.mojang/minecraftWorlds/foo`..
+    |-- - db/000002.ldb (5 B)
+    |-- + db/000003.ldb (6 B)
+    |-- ~ level.dat (4 B -> 4 B)
+    `-- + world_icon.jpeg (5 B)
+    info: 2 added, 1 changed, 1 removed
+    "#);
+
+    #[cfg(windows)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: comparing `worlds\foo` with `com.mojang\minecraftWorlds\foo`..
+    |-- - db\000002.ldb (5 B)
+    |-- + db\000003.ldb (6 B)
+    |-- ~ level.dat (4 B -> 4 B)
+    `-- + world_icon.jpeg (5 B)
+    info: 2 added, 1 changed, 1 removed
+    "#);
+}
+
+#[test]
+fn diff_as_json() {
+    let mut test = HazeTest::new(
+        fn_name!(),
+        ["diff", "foo", "--checksum", "--format", "json"],
+        Some(COM_MOJANG),
+    );
+
+    #[cfg(unix)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    {
+      "name": "foo",
+      "localPath": "worlds/foo",
+      "comMojangPath": "com.mojang/minecraftWorlds/foo",
+      "added": 2,
+      "changed": 1,
+      "removed": 1,
+      "files": [
+        {
+          "path": "db/000002.ldb",
+          "status": "removed",
+          "localSize": 5,
+          "comMojangSize": null
+        },
+        {
+          "path": "db/000003.ldb",
+          "status": "added",
+          "localSize": null,
+          "comMojangSize": 6
+        },
+        {
+          "path": "level.dat",
+          "status": "changed",
+          "localSize": 4,
+          "comMojangSize": 4
+        },
+        {
+          "path": "world_icon.jpeg",
+          "status": "added",
+          "localSize": null,
+          "comMojangSize": 5
+        }
+      ]
+    }
+
+    ----- stderr -----
+    "#);
+
+    #[cfg(windows)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    {
+      "name": "foo",
+      "localPath": "worlds\\foo",
+      "comMojangPath": "com.mojang\\minecraftWorlds\\foo",
+      "added": 2,
+      "changed": 1,
+      "removed": 1,
+      "files": [
+        {
+          "path": "db\\000002.ldb",
+          "status": "removed",
+          "localSize": 5,
+          "comMojangSize": null
+        },
+        {
+          "path": "db\\000003.ldb",
+          "status": "added",
+          "localSize": null,
+          "comMojangSize": 6
+        },
+        {
+          "path": "level.dat",
+          "status": "changed",
+          "localSize": 4,
+          "comMojangSize": 4
+        },
+        {
+          "path": "world_icon.jpeg",
+          "status": "added",
+          "localSize": null,
+          "comMojangSize": 5
+        }
+      ]
+    }
+
+    ----- stderr -----
+    "#);
+}
+
+#[test]
+fn status() {
+    let mut test = HazeTest::new(fn_name!(), ["status"], Some(COM_MOJANG));
+    let local = test.temp_dir.join("worlds");
+    let com_mojang = test.temp_dir.join(COM_MOJANG).join(MINECRAFT_WORLDS);
+    set_modified(&test.temp_dir, 1_000);
+    set_modified(&com_mojang.join("game").join("level.dat"), 2_000);
+    set_modified(&local.join("local").join("level.dat"), 2_000);
+    set_modified(&local.join("diverged").join("level.dat"), 2_000);
+    set_modified(&com_mojang.join("diverged").join("db"), 2_000);
+
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    info: checking the status of all worlds..
+    |-- bar (only local)
+    |-- baz (only in com.mojang)
+    |-- diverged (modified both in game and locally)
+    |-- foo (identical)
+    |-- game (modified in game)
+    `-- local (modified locally)
+    "#);
+}
+
+#[test]
+fn export_over_changes() {
+    let mut test = HazeTest::new(
+        fn_name!(),
+        ["export", "foo", "--overwrite"],
+        Some(COM_MOJANG),
+    );
+
+    assert_cmd_snapshot!(test.command, @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: `foo` was changed in `com.mojang` since it was last synced
+      help: run `haze diff foo` to see the changes, or use --force to overwrite
+            them
+    "#);
+
+    let _settings = filter_timestamps();
+    test.command.arg("--force");
+    #[cfg(unix)]
+    assert_cmd_snapshot!(test.command, @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    warning: overwriting changes made to `com.mojang/minecraftWorlds/foo` since it was last synced
+    info: backed up `com.mojang/minecraftWorlds/foo` to `.haze/backups/foo/[TIMESTAMP]/com.mojang`
+    info: exported `worlds/foo` to `com.mojang/minecraftWorlds/foo`
+    "#);
+
+    let state = fs::read_to_string(test.temp_dir.join(".haze").join("state.json")).unwrap();
+    assert!(
+        !state.contains("0000000000000000"),
+        "expected the state of `foo` to have been updated"
+    );
+}
+
+#[test]
+fn export_over_unchanged_metadata() {
+    let mut test = HazeTest::new(
+        fn_name!(),
+        [
+            "export",
+            "foo",
+            "--overwrite",
+            "--incremental",
+            "--checksum",
+        ],
+        Some(COM_MOJANG),
+    );
+    set_modified(&test.temp_dir, 1_000);
+    assert!(test.command.output().unwrap().status.success());
+
+    // Change the world in a way that keeps the size and modification time of
+    // the file, which only its contents give away.
+    let com_mojang_world = test
+        .temp_dir
+        .join(COM_MOJANG)
+        .join(MINECRAFT_WORLDS)
+        .join("foo");
+    fs::write(com_mojang_world.join("marker.txt"), "saved\n").unwrap();
+    set_modified(&test.temp_dir, 1_000);
+
+    let mut command = test.command(["export", "foo", "--overwrite"]);
+    assert_cmd_snapshot!(command, @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: `foo` was changed in `com.mojang` since it was last synced
+      help: run `haze diff foo` to see the changes, or use --force to overwrite
+            them
+    "#);
+}
+
+#[test]
+#[cfg(unix)]
+fn import_world_in_use() {
+    let mut test = HazeTest::new(fn_name!(), ["import", "foo"], Some(COM_MOJANG));
+    set_modified(&test.temp_dir, 1_000);
+    let lock = fs::File::options()
+        .read(true)
+        .write(true)
+        .open(
+            test.temp_dir
+                .join(COM_MOJANG)
+                .join(MINECRAFT_WORLDS)
+                .join("foo")
+                .join("db")
+                .join("LOCK"),
+        )
+        .unwrap();
+    // Lock the file the way LevelDB does, with a POSIX record lock.
+    lock_record(&lock);
+
+    assert_cmd_snapshot!(test.command, @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: `foo` seems to be open in Minecraft, since its database is locked
+      help: save and quit the world in Minecraft, or use --force if you are sure
+            it is closed
+    "#);
+}
+
+#[test]
+#[cfg(unix)]
+fn restore_world_in_use() {
+    let mut test = HazeTest::new(
+        fn_name!(),
+        ["restore", "foo", "--target", "com.mojang"],
+        Some(COM_MOJANG),
+    );
+    set_modified(&test.temp_dir, 1_000);
+    let world = test
+        .temp_dir
+        .join(COM_MOJANG)
+        .join(MINECRAFT_WORLDS)
+        .join("foo");
+    let lock = fs::File::options()
+        .read(true)
+        .write(true)
+        .open(world.join("db").join("LOCK"))
+        .unwrap();
+    lock_record(&lock);
+
+    assert_cmd_snapshot!(test.command, @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: `foo` seems to be open in Minecraft, since its database is locked
+      help: save and quit the world in Minecraft, or use --force if you are sure
+            it is closed
+    "#);
+    assert!(!world.join("marker.txt").exists());
+
+    assert!(test
+        .command(["restore", "foo", "--target", "com.mojang", "--force"])
+        .output()
+        .unwrap()
+        .status
+        .success());
+    assert!(world.join("marker.txt").exists());
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn watch() {
+    let test = HazeTest::new(fn_name!(), ["watch"], Some(COM_MOJANG));
+    set_modified(&test.temp_dir, 1_000);
+    let mut child = process::Command::new(get_cargo_bin("haze"))
+        .args(["watch", "foo", "--settle", "0"])
+        .current_dir(&test.temp_dir)
+        .env("COM_MOJANG", COM_MOJANG)
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stderr = BufReader::new(child.stderr.take().unwrap());
+
+    // Only change the world once Haze is watching it. Saving the database
+    // must not hold up the import for longer than the settle duration.
+    let mut line = String::new();
+    stderr.read_line(&mut line).unwrap();
+    assert!(line.contains("watching"), "unexpected output: {line}");
+    let world = test
+        .temp_dir
+        .join(COM_MOJANG)
+        .join(MINECRAFT_WORLDS)
+        .join("foo");
+    fs::write(world.join("db").join("000003.log"), "saved\n").unwrap();
+    fs::write(world.join("level.dat"), "played\n").unwrap();
+
+    let local = test.temp_dir.join("worlds").join("foo").join("level.dat");
+    let imported = (0..100).any(|_| {
+        thread::sleep(Duration::from_millis(100));
+        fs::read_to_string(&local).unwrap_or_default() == "played\n"
+    });
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    assert!(imported, "expected `foo` to have been imported");
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn session() {
+    let test = HazeTest::new(fn_name!(), ["session"], Some(COM_MOJANG));
+    set_modified(&test.temp_dir, 1_000);
+    let mut child = process::Command::new(get_cargo_bin("haze"))
+        .args(["session", "foo", "bar", "--settle", "0"])
+        .current_dir(&test.temp_dir)
+        .env("COM_MOJANG", COM_MOJANG)
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stderr = BufReader::new(child.stderr.take().unwrap());
+
+    // Only play the world once Haze has exported it and is waiting.
+    let mut line = String::new();
+    while !line.contains("play the worlds") {
+        line.clear();
+        assert_ne!(stderr.read_line(&mut line).unwrap(), 0, "haze exited early");
+    }
+    fs::write(
+        test.temp_dir
+            .join(COM_MOJANG)
+            .join(MINECRAFT_WORLDS)
+            .join("foo")
+            .join("level.dat"),
+        "played\n",
+    )
+    .unwrap();
+
+    let mut rest = String::new();
+    let exited = (0..100).any(|_| {
+        thread::sleep(Duration::from_millis(100));
+        child.try_wait().unwrap().is_some()
+    });
+    if !exited {
+        child.kill().unwrap();
+    }
+    stderr.read_to_string(&mut rest).unwrap();
+    child.wait().unwrap();
+
+    assert!(
+        exited,
+        "expected the session to end once `foo` was imported"
+    );
+    assert!(
+        rest.contains("bar (not played)"),
+        "unexpected output: {rest}"
+    );
+    assert!(
+        rest.contains("foo (0 added, 1 changed, 0 removed)"),
+        "unexpected output: {rest}"
+    );
+    assert_eq!(
+        fs::read_to_string(test.temp_dir.join("worlds").join("foo").join("level.dat")).unwrap(),
+        "played\n"
+    );
+}